@@ -28,7 +28,8 @@
 //! my first attempts. If you find the code snippets to complicated, suggestions are welcome.
 //!
 //! The tasks saved in a CSV file, the enviroment of Linux and Windows is supported, the tasks saved
-//! in the users HOME directory.
+//! in the users HOME directory (```~/.local/share/todo``` or ```$XDG_DATA_HOME/todo```, the configuration
+//! in ```~/.config/todo``` or ```$XDG_CONFIG_HOME/todo```). The old directory ```~/.r_todo``` is moved there.
 //!
 //! # Usage:
 //!
@@ -62,14 +63,211 @@
 //! After removing a task, all tasks get a new consecutive ID, show the ```todo list``` to view the
 //! new ID's
 //!
+//! **Check the database and repair it:**
+//!
+//! ```todo check```
+//!
+//! Damaged lines are reported with their line number, after a confirmation the unreadable lines are
+//! moved into the file ```todo.data.rejected``` and all tasks get a new consecutive ID.
+//!
+//! **Storage backends:**
+//!
+//! The database is a simple text file by default, with the key ```storage``` of the configuration
+//! the backend can be changed to ```json``` (```todo.json```), ```todotxt``` (```todo.txt``` in the format
+//! of [todo.txt](http://todotxt.org)) or ```sqlite``` (```todo.sqlite```). The fields without a place in
+//! todo.txt are stored as tags like ```note:``` or ```created:```, so todo.txt keeps all fields too.
+//! The SQLite backend is only available if todo was built with ```cargo build --features sqlite```.
+//!
+//! **Export and import the tasks:**
+//!
+//! ```todo export --format json --output tasks.json```
+//!
+//! ```todo import tasks.json```
+//!
+//! The import adds the tasks to the list and skips tasks with the same permanent ID (a file without
+//! IDs like a Markdown checklist is compared by the text), with ```--replace```
+//! the whole list is replaced. Without ```--output``` the export is printed. The formats are ```json```,
+//! ```todotxt```, ```markdown``` (a checklist ```- [ ] task```) and ```ical``` (```VTODO``` in an ```.ics```
+//! file for calendar clients), the import uses the extension of the
+//! file. A finished task in an imported checklist marks the same task in the list as done, so e.g. a
+//! ```TODO.md``` can be synced with ```todo export -f markdown -o TODO.md``` and ```todo import TODO.md```.
+//!
+//! **Backups:**
+//!
+//! Before every change a snapshot of the database is stored in the directory ```backups```, the
+//! newest 20 snapshots are kept (change it with the key ```backup.keep``` of the configuration).
+//!
+//! ```todo backups``` shows all snapshots and ```todo restore 2``` restores the second newest,
+//! without a number the newest snapshot is restored.
+//!
+//! **Undo and redo:**
+//!
+//! ```todo undo``` reverts the last change, ```todo undo 3``` the last three changes and ```todo redo```
+//! applies an undone change again. ```todo undo --list``` shows the recorded changes.
+//!
+//! **Configuration:**
+//!
+//! The file ```todo.conf``` in ```$XDG_CONFIG_HOME/todo``` (```~/.config/todo```, on Windows in the
+//! home directory) contains ```key = value``` lines:
+//!
+//! ```color``` (```auto```, ```always``` or ```never```), ```confirm``` (```false``` never asks before
+//! ```remove``` and ```reset```), ```backup.keep```, ```date_format``` (e.g. ```%d.%m.%Y %H:%M```),
+//! ```list.filter``` (```all```, ```open``` or ```done```), ```storage```, ```db``` (the path of the
+//! database file), ```global``` and command aliases like ```alias.ls = list --open```. An alias can't
+//! replace a command, e.g. ```alias.list``` is refused.
+//!
+//! ```todo config list``` shows all keys, ```todo config get color```, ```todo config set confirm false```
+//! and ```todo config unset confirm``` change the file. Every key can be overridden with an environment
+//! variable, e.g. ```TODO_COLOR=never``` or ```TODO_DB=work.data```.
+//!
+//! **Use another database:**
+//!
+//! ```todo --db ~/work.data list``` or ```todo --data-dir /tmp/todo add a task```
+//!
+//! The global options in front of the command select the database file or its directory for every
+//! command, e.g. also ```reset``` and ```restore```. The backups and the undo journal are stored next
+//! to this database. The same can be set with the keys ```db``` and ```data_dir``` of the configuration.
+//!
+//! **Named lists:**
+//!
+//! ```todo lists create work``` creates a new list, ```todo --list work add a task``` adds a task to it.
+//!
+//! ```todo lists``` shows all lists with the number of open and done tasks, ```todo list --all-lists```
+//! shows the tasks of all lists. ```todo move 2 work``` moves a task into another list,
+//! ```todo lists rename work job``` and ```todo lists delete job``` rename and delete a list. The lists
+//! are stored in the directory ```lists``` next to the database, the key ```list``` of the configuration
+//! selects the list used without ```--list```.
+//!
+//! **Task list of a project:**
+//!
+//! ```todo init``` creates the file ```.todo``` in the working directory. In this directory and all
+//! subdirectories the tasks are stored in this file (it is searched like git searches ```.git```), so a
+//! repository can carry its own task list. ```todo --global list``` (or ```TODO_GLOBAL=true```) uses the
+//! global list instead, ```todo list``` shows the file in use below the tasks. The journal, the lock
+//! file and the backups of the project are stored in ```.todo.journal```, ```.todo.lock``` and
+//! ```.todo.backups```.
+//!
+//! **Format versions:**
+//!
+//! Every database file stores the version of its format. A file of an older version is converted
+//! once into the current format, the old file is kept as e.g. ```todo.data.v1.bak```. A file written
+//! by a newer version of todo is never loaded or saved, update todo instead.
+//!
+//! **Priorities:**
+//!
+//! ```todo add -p high call the bank``` adds a task with a priority, ```todo prio 3 B``` changes it and
+//! ```todo prio 3 none``` removes it. A priority is a letter from ```A``` (the highest) to ```Z```, the
+//! names ```high```, ```medium``` and ```low``` are ```A```, ```B``` and ```C```.
+//!
+//! ```todo list --sort prio``` shows the most important tasks first, ```todo list --priority B``` only the
+//! tasks with the priority ```B``` or higher. The priority is also exported, e.g. as ```(A)``` in todo.txt
+//! and Markdown and as ```PRIORITY``` in iCalendar.
+//!
+//! **Due dates:**
+//!
+//! ```todo add --due friday send the report``` adds a task with a due date, ```todo due 3 in 2 weeks```
+//! changes it and ```todo due 3 none``` removes it. A date is an ISO date (```2024-05-10```),
+//! ```today```, ```tomorrow```, a weekday (```friday``` or ```next friday```), ```next week```,
+//! ```in 3 days```, ```in 2 weeks```, ```in 1 month``` or the end of the week, month or year (```eow```,
+//! ```eom```, ```eoy```). All dates are UTC.
+//!
+//! ```todo list``` shows e.g. ```(due in 2 days)``` or ```(overdue by 1 day)``` after the age of a task,
+//! overdue tasks are red. ```todo due``` shows the open tasks with a due date, the next deadline first,
+//! ```todo due --days 7``` only the overdue tasks and the deadlines of the next week.
+//! ```todo list --sort due``` sorts the whole list by the due dates.
+//!
+//! **Tags and contexts:**
+//!
+//! Words like ```+project``` and ```@context``` in a task are its tags, ```todo add Ship the
+//! release +v2 @work``` adds a task with the tags ```+v2``` and ```@work```, a tag can also be given
+//! with ```--tag v2``` (without ```+``` or ```@``` it's a project). ```todo edit``` replaces the tags,
+//! if the new task has tags. ```todo list``` shows the tags after the task. A tag starts with a
+//! letter, ```call +49 30 123``` or ```mail @ 5pm``` have no tags.
+//!
+//! ```todo list +v2 -@home``` shows only the tasks with the tag ```+v2```, but without ```@home```,
+//! ```todo tags``` shows all tags with the number of open and all tasks.
+//!
+//! **Completed tasks:**
+//!
+//! ```todo done 3``` records the time of completion, ```todo list``` shows e.g. ```(done 3 hour(s) ago,
+//! after 2 day(s))``` behind a finished task. A task marked as done by mistake is reopened with
+//! ```todo undone 3```, this removes the time of completion.
+//!
+//! **Subtasks:**
+//!
+//! ```todo add --parent 4 write the changelog``` adds a subtask of the task 4, a subtask can have
+//! subtasks too. ```todo list``` shows the subtasks indented under their parent and the progress of
+//! the parent, e.g. ```(2/5)``` finished subtasks. ```todo done 4``` asks to mark the open subtasks
+//! as done too, ```todo done 4 --children``` does it without asking.
+//!
+//! ```todo remove 4``` removes the task with all its subtasks, ```todo remove 4 --keep-children```
+//! keeps them as subtasks of the parent of the removed task. ```todo move 4 work``` moves the task
+//! with its subtasks.
+//!
+//! **Dependencies:**
+//!
+//! ```todo block 7 --by 3``` lets the task 7 depend on the task 3, it's blocked until the task 3 is
+//! done (```--by 3,4``` for more tasks). A dependency, which would be a cycle, is rejected.
+//! ```todo unblock 7 --by 3``` removes one dependency, ```todo unblock 7``` all of them.
+//!
+//! ```todo list``` shows the blocked tasks dimmed with their open blockers, e.g. ```(blocked by 3)```,
+//! ```todo ready``` shows only the open tasks, which are not blocked. When ```todo done``` finishes
+//! the last blocker of a task, the task is reported as unblocked. The subtasks and the dependencies
+//! are linked by the permanent IDs of the tasks, so they keep their tasks, when the numbers change.
+//!
+//! **Recurring tasks:**
+//!
+//! ```todo add --every 1w submit timesheet``` adds a recurring task, a rule is an interval like ```1d```,
+//! ```2w```, ```1m``` or ```1y``` (```daily```, ```weekly```, ```monthly```, ```yearly```), ```weekdays```
+//! or a weekday like ```friday```. These rules follow the due dates, an interval with ```+``` like
+//! ```+3d``` is the time after the task was done. A monthly task on the 31th is due on the last day
+//! of a shorter month and on the 31th again after it, the rule keeps the day like ```1m@31```.
+//!
+//! When ```todo done``` finishes a recurring task, the next occurrence is added as new task with the
+//! next due date. ```todo recur``` shows the recurring tasks, ```todo recur 5 2w``` changes the rule
+//! and ```todo recur 5 stop``` stops the recurrence.
+//!
+//! **Notes:**
+//!
+//! ```todo note 5``` opens the notes of a task in ```$VISUAL``` or ```$EDITOR```, ```todo note 5 call
+//! back on monday``` appends a line and ```todo note 5 --clear``` removes the notes. A task with notes
+//! is marked with ```[notes]``` in the list, ```todo show 5``` shows all details and the notes of a
+//! task.
+//!
+//! **Permanent IDs:**
+//!
+//! The number of a task is only its position in the list, it changes when a task before it is removed.
+//! Every task has a permanent ID too, a UUID like ```6f1c2a9e-3b4d-4e5f-8a7b-9c0d1e2f3a4b```, which
+//! never changes. All commands accept the number or the start of the permanent ID, e.g.
+//! ```todo done 6f1c2a```, a start without a letter needs a ```#``` like ```todo done #123456```.
+//! ```todo list --uid``` shows the shortest unique start of the IDs and ```todo show 5``` the whole ID.
+//!
+//! **Time tracking:**
+//!
+//! ```todo start 4``` starts the time tracking of a task and ```todo stop``` stops it, only one task is
+//! tracked at a time, so starting another task stops the running one. A finished task is stopped too.
+//! The running task is marked with ```[>]``` in the list.
+//!
+//! ```todo time``` shows the tracked time per task, ```--by day``` per day and ```--by tag``` per tag.
+//! The range is ```--today```, ```--week```, ```--month``` or ```--from 2024-05-01 --to 2024-05-31```,
+//! with ```--csv``` or ```--output may.csv``` the report is a CSV table for a timesheet.
+//!
 //!
 //! *This cli command can be used in scripts, but the user interaction e.g. ```todo reset```
 //! will always be aborted.*
 //!
 
+mod todo_config;
+mod todo_date;
+mod todo_export;
+mod todo_journal;
+mod todo_json;
 mod todo_lib;
+mod todo_migrate;
+mod todo_storage;
 
 use std::env;
+use crate::todo_config::{config, config_command, init_config, CMD_CONFIG};
 use crate::todo_lib::*;
 
 /// like every Rust binary, this is the entry function.
@@ -77,74 +275,187 @@ use crate::todo_lib::*;
 /// The parsing of the command line arguments are done here with ```Vec<String>```.
 fn main() -> std::result::Result<(), usize>{
 
+    let prg_name = env::args().next().unwrap();
+    let version = env!("CARGO_PKG_VERSION");
+    let mut argv: Vec<String> = env::args().skip(1).collect();
+
+    // the global options --db and --data-dir are given in front of the command
+    init_config(&mut argv);
+
+    if argv.is_empty() {
+        println!("{} needs at least one argument, try --help", prg_name);
+        return Ok(());
+    }
+
+    // a command alias of the configuration is replaced by its command and arguments
+    if let Some(mut alias) = config().alias(&argv[0]) {
+        alias.extend(argv.drain(1..));
+        argv = alias;
+    }
+
+    // these commands don't need the database
+    match argv[0].to_ascii_lowercase().as_str() {
+
+        CMD_CONFIG => {
+            config_command(&argv[1..]);
+            return Ok(());
+        }
+
+        CMD_LISTS => {
+            lists(&argv[1..]);
+            return Ok(());
+        }
+
+        CMD_INIT => {
+            init();
+            return Ok(());
+        }
+
+        "-h" | "--help" | CMD_HELP => {
+            help(&prg_name);
+            return Ok(());
+        }
+
+        "-v" | "--version" => {
+                println!("{} v{}\n", prg_name, version);
+                return Ok(());
+        }
+
+        _ => {}
+
+    }
+
+    // the lock is held until the end of main(), another todo process waits until the database is saved
+    let _lock = lock_database();
+
     // loading the CSV file and initialize it as Vector in a Structure
     let mut todo_db = TodoDatabase::load();
 
-    let prg_name = env::args().nth(0).unwrap();
-    let version = option_env!("CARGO_PKG_VERSION").unwrap();
-    let argv: Vec<String> = env::args().skip(1).collect();
+    match argv[0].to_ascii_lowercase().as_str() {
 
-    if argv.len() >= 1 {
+        CMD_ADD => {
+            todo_db.add(&argv[1..]);
+        }
 
-        match argv[0].to_ascii_lowercase().as_str() {
+        CMD_BACKUPS => {
+            todo_db.backups();
+        }
 
-            CMD_ADD => {
-                todo_db.add(&argv[1..]);
-            }
+        CMD_BLOCK => {
+            todo_db.block(&argv[1..]);
+        }
 
-            CMD_DONE => {
-                todo_db.done(&argv[1..]);
-            }
+        CMD_CHECK | "fsck" => {
+            todo_db.check(&argv[1..]);
+        }
 
-            CMD_EDIT => {
-                todo_db.edit(&argv[1..]);
-            }
+        CMD_DONE => {
+            todo_db.done(&argv[1..]);
+        }
 
-            CMD_LIST => {
-                todo_db.list();
-            }
+        CMD_DUE => {
+            todo_db.due(&argv[1..]);
+        }
 
-            CMD_REMOVE => {
-                todo_db.remove(&argv[1..]);
-            }
+        CMD_EDIT => {
+            todo_db.edit(&argv[1..]);
+        }
 
-            CMD_RESET => {
-                todo_db.reset();
-            }
+        CMD_EXPORT => {
+            todo_db.export(&argv[1..]);
+        }
 
-            CMD_RESTORE => {
-                todo_db.restore();
-            }
+        CMD_IMPORT => {
+            todo_db.import(&argv[1..]);
+        }
 
-            // maybe in the future
-            // "-i" | "--interactive" => {
-            //     // TODO: start interactive mode
-            // }
+        CMD_LIST => {
+            todo_db.list(&argv[1..]);
+        }
 
-            "-h" | "--help" | CMD_HELP => {
-                help(&prg_name);
-                return Ok(());
-            }
+        CMD_MOVE => {
+            todo_db.move_task(&argv[1..]);
+        }
 
-            "-v" | "--version" => {
-                    println!("{} v{}\n", prg_name, version);
-                    return Ok(());
-            }
+        CMD_NOTE | "notes" => {
+            todo_db.note(&argv[1..]);
+        }
 
-            _ => {
-                eprintln!("Unknown argument, try {} --help", prg_name);
-                return Ok(());
-            }
+        CMD_PRIO | "priority" => {
+            todo_db.prio(&argv[1..]);
+        }
 
+        CMD_READY => {
+            todo_db.ready();
         }
 
-    } else {
+        CMD_RECUR => {
+            todo_db.recur(&argv[1..]);
+        }
 
-        println!("{} needs at least one argument, try --help", prg_name);
+        CMD_REDO => {
+            todo_db.redo(&argv[1..]);
+        }
+
+        CMD_REMOVE => {
+            todo_db.remove(&argv[1..]);
+        }
+
+        CMD_RESET => {
+            todo_db.reset();
+        }
+
+        CMD_RESTORE => {
+            todo_db.restore(&argv[1..]);
+        }
+
+        CMD_SHOW => {
+            todo_db.show(&argv[1..]);
+        }
+
+        CMD_START => {
+            todo_db.start(&argv[1..]);
+        }
+
+        CMD_STOP => {
+            todo_db.stop();
+        }
+
+        CMD_TAGS => {
+            todo_db.tags();
+        }
+
+        CMD_TIME => {
+            todo_db.time(&argv[1..]);
+        }
+
+        CMD_UNBLOCK => {
+            todo_db.unblock(&argv[1..]);
+        }
+
+        CMD_UNDO => {
+            todo_db.undo(&argv[1..]);
+        }
+
+        CMD_UNDONE => {
+            todo_db.undone(&argv[1..]);
+        }
+
+        // maybe in the future
+        // "-i" | "--interactive" => {
+        //     // TODO: start interactive mode
+        // }
+
+        _ => {
+            eprintln!("Unknown argument, try {} --help", prg_name);
+            return Ok(());
+        }
 
     }
 
-    todo_db.save();
+    if todo_db.is_changed() {
+        todo_db.save(&argv.join(" "));
+    }
 
     Ok(())
 }
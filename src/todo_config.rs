@@ -0,0 +1,401 @@
+//! The configuration file ```todo.conf```.
+//!
+//! The file is stored in ```$XDG_CONFIG_HOME/todo``` (```~/.config/todo```), every line is a ```key = value``` pair and lines
+//! starting with ```#``` are comments. Every key can be overridden with an environment variable,
+//! the name is ```TODO_``` and the key in upper case with ```_``` instead of ```.```, e.g.
+//! ```TODO_COLOR=never``` or ```TODO_BACKUP_KEEP=5```. The global options ```--db```,
+//! ```--data-dir``` and ```--list``` override the environment variables and the file.
+//!
+//! **Commands:**
+//!
+//! ```todo config list```, ```todo config get color```, ```todo config set alias.ls list```
+//! and ```todo config unset alias.ls```
+
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::OnceLock;
+
+use crate::todo_lib::{get_config_dir, is_command};
+use crate::todo_storage::write_atomic;
+
+pub const CMD_CONFIG: &str = "config";
+
+pub const APP_CONFIG: &str = "todo.conf";
+
+// the prefix of the command aliases, e.g. alias.ls = list
+const ALIAS_PREFIX: &str = "alias.";
+
+/// All known keys with their default value and a short description.
+const KEYS: [(&str, &str, &str); 11] = [
+    ("color", "auto", "colourful output: auto, always or never"),
+    ("confirm", "true", "ask before remove and reset: true or false"),
+    ("backup.keep", "20", "number of snapshots in the backups directory, 0 disables them"),
+    ("date_format", "%Y-%m-%d %H:%M:%S", "format of dates: %Y %m %d %H %M %S"),
+    ("list.filter", "all", "tasks shown by list: all, open or done"),
+    ("storage", "auto", "storage backend: auto, csv, json, todotxt or sqlite"),
+    ("db", "", "path of the database file, empty for the default"),
+    ("data_dir", "", "directory of the database, empty for the default"),
+    ("list", "default", "the selected list, see the command lists"),
+    ("global", "false", "use the global list instead of the task list of a project: true or false"),
+    ("alias.NAME", "", "a command alias, e.g. alias.ls = list"),
+];
+
+/// The settings of the configuration file, the environment variables are read on demand.
+pub struct Config {
+    path: PathBuf,
+    values: Vec<(String, String)>,
+    options: Vec<(String, String)>,
+}
+
+impl Config {
+
+    /// Load the configuration file, a missing file is an empty configuration.
+    pub fn load(path: &Path) -> Config {
+        let mut values: Vec<(String, String)> = Vec::new();
+        let content = fs::read_to_string(path).unwrap_or_default();
+        for (nr, line) in content.lines().enumerate() {
+            let l = line.trim();
+            if l.starts_with('#') || l.is_empty() {
+                continue;
+            }
+            match l.split_once('=') {
+                Some((key, value)) => values.push((key.trim().to_string(), value.trim().to_string())),
+                None => eprintln!("Ignoring line {} of {}: {}", nr + 1, path.display(), line),
+            }
+        }
+        Config { path: path.to_path_buf(), values, options: Vec::new() }
+    }
+
+    /// The value of a key: the command line option, the environment variable, the configuration
+    /// file or the default.
+    pub fn get(&self, key: &str) -> String {
+        if let Some((_, value)) = self.options.iter().find(|(k, _)| k == key) {
+            return value.to_string();
+        }
+        if let Ok(value) = env::var(env_name(key)) {
+            return value;
+        }
+        if let Some((_, value)) = self.values.iter().rev().find(|(k, _)| k == key) {
+            return value.to_string();
+        }
+        KEYS.iter().find(|(k, _, _)| *k == key).map(|(_, d, _)| d.to_string()).unwrap_or_default()
+    }
+
+    /// Where the value of a key comes from.
+    fn source(&self, key: &str) -> String {
+        if self.options.iter().any(|(k, _)| k == key) {
+            format!("option --{}", key.replace('_', "-"))
+        } else if env::var(env_name(key)).is_ok() {
+            format!("environment {}", env_name(key))
+        } else if self.values.iter().any(|(k, _)| k == key) {
+            String::from("file")
+        } else {
+            String::from("default")
+        }
+    }
+
+    /// Should the output be colourful, ```auto``` uses colours only in a terminal.
+    pub fn color(&self) -> bool {
+        match self.get("color").to_ascii_lowercase().as_str() {
+            "always" | "true" | "yes" => true,
+            "never" | "false" | "no" => false,
+            _ => io::stdout().is_terminal(),
+        }
+    }
+
+    /// Should the user confirm remove and reset.
+    pub fn confirm(&self) -> bool {
+        !matches!(self.get("confirm").to_ascii_lowercase().as_str(), "false" | "no" | "0")
+    }
+
+    /// The number of kept snapshots.
+    pub fn backup_keep(&self) -> usize {
+        let value = self.get("backup.keep");
+        value.parse::<usize>().unwrap_or_else(|_| {
+            eprintln!("Invalid value for backup.keep: {}", value);
+            20
+        })
+    }
+
+    /// The format of the dates.
+    pub fn date_format(&self) -> String {
+        self.get("date_format")
+    }
+
+    /// The tasks shown by ```list``` without options.
+    pub fn list_filter(&self) -> String {
+        self.get("list.filter").to_ascii_lowercase()
+    }
+
+    /// The storage backend, an empty string selects it by the extension of the database file.
+    pub fn storage(&self) -> String {
+        match self.get("storage").to_ascii_lowercase().as_str() {
+            "auto" => String::new(),
+            kind => kind.to_string(),
+        }
+    }
+
+    /// The path of the database file, if it is set.
+    pub fn db(&self) -> Option<PathBuf> {
+        let db = self.get("db");
+        if db.is_empty() { None } else { Some(PathBuf::from(db)) }
+    }
+
+    /// The directory of the database, if it is set.
+    pub fn data_dir(&self) -> Option<PathBuf> {
+        let dir = self.get("data_dir");
+        if dir.is_empty() { None } else { Some(PathBuf::from(dir)) }
+    }
+
+    /// The name of the selected list.
+    pub fn list(&self) -> String {
+        self.get("list")
+    }
+
+    /// Should the global list be used instead of the task list of a project, it's usually set with
+    /// the option ```--global``` or the environment variable ```TODO_GLOBAL```.
+    pub fn global(&self) -> bool {
+        matches!(self.get("global").to_ascii_lowercase().as_str(), "true" | "yes" | "1")
+    }
+
+    /// The command and arguments of an alias, the value is split at the spaces. An alias with the
+    /// name of a command is ignored, it can't replace the command.
+    pub fn alias(&self, name: &str) -> Option<Vec<String>> {
+        if is_command(name) {
+            return None;
+        }
+        let value = self.get(format!("{}{}", ALIAS_PREFIX, name).as_str());
+        if value.is_empty() {
+            return None;
+        }
+        Some(value.split_whitespace().map(|a| a.to_string()).collect())
+    }
+
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// The configuration of this program, it is loaded once.
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(|| Config::load(&get_config_dir().join(APP_CONFIG)))
+}
+
+/// Remove the global options ```--db PATH```, ```--data-dir DIR```, ```--list NAME``` and ```--global```
+/// in front of the command from the arguments and load the configuration with them.
+pub fn init_config(argv: &mut Vec<String>) {
+
+    let mut options: Vec<(String, String)> = Vec::new();
+    while let Some(arg) = argv.first().cloned() {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name.to_string(), Some(value.to_string())),
+            None => (arg, None),
+        };
+        if name == "-g" || name == "--global" {
+            argv.remove(0);
+            options.push((String::from("global"), String::from("true")));
+            continue;
+        }
+        let key = match name.as_str() {
+            "--db" => "db",
+            "--data-dir" => "data_dir",
+            "--list" => "list",
+            _ => break,
+        };
+        argv.remove(0);
+        let value = match value {
+            Some(value) => value,
+            None if !argv.is_empty() => argv.remove(0),
+            None => {
+                eprintln!("Missing value for option {}", name);
+                process::exit(1)
+            }
+        };
+        options.push((key.to_string(), value));
+    }
+
+    let mut cfg = Config::load(&get_config_dir().join(APP_CONFIG));
+    cfg.options = options;
+    let _ = CONFIG.set(cfg);
+
+}
+
+/// For internal use.
+///
+/// The name of the environment variable for a key, ```db``` is ```TODO_DB```.
+fn env_name(key: &str) -> String {
+    format!("TODO_{}", key.to_ascii_uppercase().replace(['.', '-'], "_"))
+}
+
+/// For internal use.
+///
+/// Is the key a known key or an alias.
+fn is_known(key: &str) -> bool {
+    KEYS.iter().any(|(k, _, _)| *k == key && !k.starts_with(ALIAS_PREFIX))
+        || (key.starts_with(ALIAS_PREFIX) && key.len() > ALIAS_PREFIX.len())
+}
+
+/// For internal use.
+///
+/// Check a value before it is saved, the result is the error message.
+fn check_value(key: &str, value: &str) -> Result<(), String> {
+    if !is_known(key) {
+        return Err(format!("Unknown key: {}", key));
+    }
+    if key.strip_prefix(ALIAS_PREFIX).is_some_and(is_command) {
+        return Err(format!("The alias {} can't replace the command of the same name.", key));
+    }
+    let valid = match key {
+        "color" => ["auto", "always", "never"].contains(&value),
+        "confirm" | "global" => ["true", "false"].contains(&value),
+        "backup.keep" => value.parse::<usize>().is_ok(),
+        "list.filter" => ["all", "open", "done"].contains(&value),
+        "storage" => ["auto", "csv", "json", "todotxt", "sqlite"].contains(&value),
+        "date_format" | "db" | "data_dir" => true,
+        "list" => !value.is_empty() && value.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_'),
+        _ => !value.is_empty(),
+    };
+    if valid { Ok(()) } else { Err(format!("Invalid value for {}: {}", key, value)) }
+}
+
+/// For internal use.
+///
+/// Change a key in the configuration file, the comments and the order of the lines are kept.
+/// Without a value the key is removed.
+fn write_value(path: &Path, key: &str, value: Option<&str>) -> Result<(), String> {
+
+    let content = fs::read_to_string(path).unwrap_or(String::from("# ToDo configuration\n"));
+    let mut lines: Vec<String> = Vec::new();
+    let mut found = false;
+
+    for line in content.lines() {
+        let is_key = line.split_once('=').map(|(k, _)| k.trim() == key).unwrap_or(false) && !line.trim_start().starts_with('#');
+        if !is_key {
+            lines.push(line.to_string());
+        } else if let (Some(v), false) = (value, found) {
+            lines.push(format!("{} = {}", key, v));
+            found = true;
+        }
+    }
+    if let (Some(v), false) = (value, found) {
+        lines.push(format!("{} = {}", key, v));
+    }
+
+    let mut s = lines.join("\n");
+    s.push('\n');
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    write_atomic(path, &s)
+
+}
+
+/// The command ```config```, show or change the configuration.
+///
+/// **Command:**
+///
+/// ```todo config list```, ```todo config get KEY```, ```todo config set KEY VALUE``` or
+/// ```todo config unset KEY```
+pub fn config_command(argv: &[String]) {
+
+    let cfg = config();
+    let sub = argv.first().map(|a| a.to_ascii_lowercase()).unwrap_or(String::from("list"));
+
+    match (sub.as_str(), argv.get(1)) {
+
+        ("list", _) => {
+            println!("Configuration file: {}\n", cfg.path.display());
+            for (key, _, desc) in KEYS.iter().filter(|(k, _, _)| !k.starts_with(ALIAS_PREFIX)) {
+                println!("{:12} = {:20} ({}, {})", key, cfg.get(key), cfg.source(key), desc);
+            }
+            let mut aliases: Vec<&String> = cfg.values.iter().map(|(k, _)| k).filter(|k| k.starts_with(ALIAS_PREFIX)).collect();
+            aliases.sort();
+            aliases.dedup();
+            for key in aliases {
+                let ignored = if is_command(&key[ALIAS_PREFIX.len()..]) { ", ignored, it's a command" } else { "" };
+                println!("{:12} = {:20} ({}{})", key, cfg.get(key), cfg.source(key), ignored);
+            }
+            println!();
+        }
+
+        ("get", Some(key)) => {
+            if !is_known(key) {
+                eprintln!("Unknown key: {}", key);
+                process::exit(1)
+            }
+            println!("{}", cfg.get(key));
+        }
+
+        ("set", Some(key)) => {
+            let value = argv[2..].join(" ");
+            if let Err(err) = check_value(key, &value) {
+                eprintln!("{}", err);
+                process::exit(1)
+            }
+            if let Err(err) = write_value(&cfg.path, key, Some(&value)) {
+                eprintln!("Can't write configuration file: {} (error: {})", cfg.path.display(), err);
+                process::exit(1)
+            }
+            println!("{} = {}", key, value);
+        }
+
+        ("unset", Some(key)) => {
+            if let Err(err) = write_value(&cfg.path, key, None) {
+                eprintln!("Can't write configuration file: {} (error: {})", cfg.path.display(), err);
+                process::exit(1)
+            }
+            println!("{} removed.", key);
+        }
+
+        _ => {
+            eprintln!("Unknown or incomplete command, try {} list|get KEY|set KEY VALUE|unset KEY", CMD_CONFIG);
+            process::exit(1)
+        }
+
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn values() {
+        assert_eq!(env_name("backup.keep"), "TODO_BACKUP_KEEP");
+        assert!(check_value("color", "never").is_ok());
+        assert!(check_value("color", "sometimes").is_err());
+        assert!(check_value("backup.keep", "5").is_ok());
+        assert!(check_value("backup.keep", "-1").is_err());
+        assert!(check_value("list", "work_2024").is_ok());
+        assert!(check_value("list", "a/b").is_err());
+        assert!(check_value("unknown", "x").is_err());
+        assert!(check_value("global", "true").is_ok());
+        assert!(check_value("global", "maybe").is_err());
+    }
+
+    #[test]
+    fn aliases() {
+        assert!(check_value("alias.ls", "list --open").is_ok());
+        assert!(check_value("alias.list", "list --open").is_err());
+        assert!(check_value("alias.Done", "remove").is_err());
+        assert!(check_value("alias.fsck", "list").is_err());
+        let mut cfg = Config::load(Path::new(""));
+        cfg.values = vec![(String::from("alias.ls"), String::from("list  --open")), (String::from("alias.list"), String::from("remove 1"))];
+        assert_eq!(cfg.alias("ls"), Some(vec![String::from("list"), String::from("--open")]));
+        assert_eq!(cfg.alias("list"), None);
+        assert_eq!(cfg.alias("unknown"), None);
+    }
+
+    #[test]
+    fn keys() {
+        for key in ["color", "confirm", "backup.keep", "date_format", "list.filter", "storage", "db", "data_dir", "list", "global"] {
+            assert!(is_known(key), "{}", key);
+        }
+        assert_eq!(env_name("global"), "TODO_GLOBAL");
+    }
+
+}
@@ -0,0 +1,427 @@
+//! Date functions for the ToDo list.
+//!
+//! To be independed from the OS time functions, the dates are calculated from the seconds since
+//! the UNIX epoch with the algorithms from Howard Hinnant (```days_from_civil()``` and
+//! ```civil_from_days()```). All dates are UTC.
+
+/// The date of the seconds since the UNIX epoch as ```(year, month, day)```.
+pub fn civil_from_secs(secs: u64) -> (i64, u32, u32) {
+    civil_from_days((secs / 86_400) as i64)
+}
+
+/// The date of the days since the UNIX epoch as ```(year, month, day)```.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (if m <= 2 { y + 1 } else { y }, m, d)
+}
+
+/// The days since the UNIX epoch of a date.
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// The number of days in a month.
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        _ => if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 { 29 } else { 28 },
+    }
+}
+
+/// The date as ```YYYY-MM-DD```.
+pub fn date_string(secs: u64) -> String {
+    let (y, m, d) = civil_from_secs(secs);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// The date and time in a format like ```strftime()```, known are ```%Y```, ```%m```, ```%d```,
+/// ```%H```, ```%M```, ```%S``` and ```%%```, other characters are copied.
+pub fn format_time(secs: u64, fmt: &str) -> String {
+    let (y, m, d) = civil_from_secs(secs);
+    let t = secs % 86_400;
+    let mut s = String::new();
+    let mut chars = fmt.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            s.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => s.push_str(format!("{:04}", y).as_str()),
+            Some('m') => s.push_str(format!("{:02}", m).as_str()),
+            Some('d') => s.push_str(format!("{:02}", d).as_str()),
+            Some('H') => s.push_str(format!("{:02}", t / 3_600).as_str()),
+            Some('M') => s.push_str(format!("{:02}", t % 3_600 / 60).as_str()),
+            Some('S') => s.push_str(format!("{:02}", t % 60).as_str()),
+            Some('%') => s.push('%'),
+            Some(other) => { s.push('%'); s.push(other); }
+            None => s.push('%'),
+        }
+    }
+    s
+}
+
+/// The date part of a format of ```format_time()```, e.g. ```%d.%m.%Y``` of ```%d.%m.%Y %H:%M```.
+/// A format without a date is ```%Y-%m-%d```.
+pub fn date_format(fmt: &str) -> String {
+    let is_time = |part: &str| ["%H", "%M", "%S"].iter().any(|t| part.contains(t));
+    let is_date = |part: &str| ["%Y", "%m", "%d"].iter().any(|d| part.contains(d));
+    // the parts are separated by spaces, e.g. "%d.%m.%Y %H:%M" or "%H:%M, %d.%m.%Y"
+    let parts: Vec<&str> = fmt.split(' ').filter(|p| !is_time(p) && is_date(p)).collect();
+    match parts.join(" ").trim_matches([',', ' ']) {
+        "" => String::from("%Y-%m-%d"),
+        date => date.to_string(),
+    }
+}
+
+/// The date and time for file names as ```YYYYMMDD-HHMMSS```.
+pub fn file_stamp(secs: u64) -> String {
+    let (y, m, d) = civil_from_secs(secs);
+    let t = secs % 86_400;
+    format!("{:04}{:02}{:02}-{:02}{:02}{:02}", y, m, d, t / 3_600, t % 3_600 / 60, t % 60)
+}
+
+/// Parse a date in the format ```YYYY-MM-DD```, the result are the seconds since the UNIX
+/// epoch at midnight.
+pub fn parse_date(s: &str) -> Option<u64> {
+    let parts: Vec<&str> = s.split('-').collect();
+    if parts.len() != 3 || parts[0].len() != 4 || parts[1].len() != 2 || parts[2].len() != 2 {
+        return None;
+    }
+    let y = parts[0].parse::<i64>().ok()?;
+    let m = parts[1].parse::<u32>().ok()?;
+    let d = parts[2].parse::<u32>().ok()?;
+    if y < 1970 || !(1..=12).contains(&m) || d == 0 || d > days_in_month(y, m) {
+        return None;
+    }
+    Some(days_from_civil(y, m, d) as u64 * 86_400)
+}
+
+// the names of the weekdays, the first three letters are the short name
+pub const WEEKDAYS: [&str; 7] = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+
+/// The day of the week of the days since the UNIX epoch, ```0``` is Monday.
+pub fn weekday(days: i64) -> u32 {
+    (days + 3).rem_euclid(7) as u32
+}
+
+/// Parse a due date, the result are the seconds since the UNIX epoch at midnight of the day.
+///
+/// Known are ISO dates (```2024-05-10```), ```today```, ```tomorrow```, weekdays (```friday```,
+/// ```fri``` or ```next friday``` is the next friday after today), ```next week``` (Monday),
+/// ```in 3 days```, ```in 2 weeks```, ```in 1 month``` and the end of the week (```eow```, Sunday),
+/// month (```eom```) and year (```eoy```).
+pub fn parse_due(s: &str, now: u64) -> Option<u64> {
+
+    let s = s.trim().to_ascii_lowercase();
+    let today = (now / 86_400) as i64;
+    let (y, m, _) = civil_from_days(today);
+    let words: Vec<&str> = s.split_whitespace().collect();
+
+    let days = match words.as_slice() {
+        [date] if date.contains('-') => return parse_date(date),
+        ["today"] => today,
+        ["tomorrow"] => today + 1,
+        ["next", "week"] => today + 7 - weekday(today) as i64,
+        ["eow"] => today + 6 - weekday(today) as i64,
+        ["eom"] => days_from_civil(y, m, days_in_month(y, m)),
+        ["eoy"] => days_from_civil(y, 12, 31),
+        ["in", n, unit] => {
+            let n = n.parse::<i64>().ok()?;
+            match unit.trim_end_matches('s') {
+                "day" => today + n,
+                "week" => today + 7 * n,
+                "month" => add_months(today, n),
+                _ => return None,
+            }
+        }
+        ["next", day] | [day] => next_weekday(today, parse_weekday(day)?),
+        _ => return None,
+    };
+
+    if days < 0 {
+        return None;
+    }
+    Some(days as u64 * 86_400)
+
+}
+
+/// For internal use.
+///
+/// Add months to the days since the UNIX epoch, the day is the last day of a shorter month.
+fn add_months(days: i64, n: i64) -> i64 {
+    add_months_on(days, n, civil_from_days(days).2)
+}
+
+/// For internal use.
+///
+/// Add months to the days since the UNIX epoch, the result is the day of the month or the last
+/// day of a shorter month.
+fn add_months_on(days: i64, n: i64, day: u32) -> i64 {
+    let (y, m, _) = civil_from_days(days);
+    let months = y * 12 + m as i64 - 1 + n;
+    let (y, m) = (months.div_euclid(12), months.rem_euclid(12) as u32 + 1);
+    days_from_civil(y, m, day.min(days_in_month(y, m)))
+}
+
+/// For internal use.
+///
+/// The weekday of a name like ```friday``` or ```fri```, ```0``` is Monday.
+fn parse_weekday(name: &str) -> Option<u32> {
+    let wd = WEEKDAYS.iter().position(|w| name.len() >= 3 && w.starts_with(name))?;
+    Some(wd as u32)
+}
+
+/// For internal use.
+///
+/// The next day with the weekday after the days since the UNIX epoch.
+fn next_weekday(days: i64, wd: u32) -> i64 {
+    let ahead = (wd as i64 - weekday(days) as i64).rem_euclid(7);
+    days + if ahead == 0 { 7 } else { ahead }
+}
+
+/// Read the rule of a recurring task, the result is the rule as it's stored.
+///
+/// Known are intervals like ```1d```, ```2w```, ```1m``` or ```1y``` (also ```daily```,
+/// ```weekly```, ```monthly``` and ```yearly```), ```weekdays``` (Monday to Friday) and a
+/// weekday like ```friday```. An interval with ```+``` like ```+3d``` is the time after the
+/// completion of the task, all other rules follow the schedule of the due dates. A monthly or
+/// yearly interval can have the day of the series like ```1m@31```, see ```anchor_recur()```.
+pub fn parse_recur(s: &str) -> Option<String> {
+    let s = s.trim().to_ascii_lowercase();
+    if let Some((rule, day)) = s.split_once('@') {
+        let rule = parse_recur(rule).filter(|r| !r.contains('@'))?;
+        let day = day.parse::<u32>().ok().filter(|d| (29..=31).contains(d) && d.to_string().len() == day.len())?;
+        return match split_interval(&rule) {
+            Some((_, 'm' | 'y')) if !rule.starts_with('+') => Some(format!("{}@{}", rule, day)),
+            _ => None,
+        };
+    }
+    let rule = match s.as_str() {
+        "daily" => String::from("1d"),
+        "weekly" => String::from("1w"),
+        "monthly" => String::from("1m"),
+        "yearly" => String::from("1y"),
+        "weekdays" => s,
+        _ => match parse_weekday(&s) {
+            Some(wd) => WEEKDAYS[wd as usize].to_string(),
+            None => {
+                let (n, unit) = split_interval(&s)?;
+                format!("{}{}{}", if s.starts_with('+') { "+" } else { "" }, n, unit)
+            }
+        },
+    };
+    Some(rule)
+}
+
+/// Add the day of the series to a monthly or yearly rule, which follows the schedule of the due
+/// dates. It's the day of the due date (or of the start of the task without due date), which
+/// isn't in every month (29th to 31th), so a series on the 31th is on the last day of a shorter
+/// month and on the 31th again after it, e.g. ```1m@31```. A day of the rule is replaced, the
+/// other rules are unchanged.
+pub fn anchor_recur(rule: &str, due: Option<u64>, start: u64) -> String {
+    let rule = rule.split_once('@').map(|(r, _)| r).unwrap_or(rule);
+    let (_, _, day) = civil_from_secs(due.unwrap_or(start));
+    match split_interval(rule) {
+        Some((_, 'm' | 'y')) if !rule.starts_with('+') && day > 28 => format!("{}@{}", rule, day),
+        _ => rule.to_string(),
+    }
+}
+
+/// For internal use.
+///
+/// Split an interval like ```2w``` or ```+3d``` into the number and the unit.
+fn split_interval(rule: &str) -> Option<(i64, char)> {
+    let rule = rule.strip_prefix('+').unwrap_or(rule);
+    let unit = rule.chars().last()?;
+    let n = rule[..rule.len() - unit.len_utf8()].parse::<i64>().ok().filter(|n| *n > 0 && *n <= 1_000)?;
+    if !"dwmy".contains(unit) {
+        return None;
+    }
+    Some((n, unit))
+}
+
+/// For internal use.
+///
+/// Add an interval to the days since the UNIX epoch.
+fn add_interval(days: i64, n: i64, unit: char) -> i64 {
+    match unit {
+        'w' => days + 7 * n,
+        'm' => add_months(days, n),
+        'y' => add_months(days, 12 * n),
+        _ => days + n,
+    }
+}
+
+/// The next due date of a recurring task, the result are the seconds since the UNIX epoch at
+/// midnight of the day. A rule, which follows the schedule, starts at the due date (or the start
+/// of the task without due date) and the missed dates are skipped, the next due date is always
+/// after the day of the completion.
+pub fn next_due(rule: &str, due: Option<u64>, start: u64, completed: u64) -> Option<u64> {
+
+    let today = (completed / 86_400) as i64;
+
+    let days = if rule.starts_with('+') {
+        let (n, unit) = split_interval(rule)?;
+        add_interval(today, n, unit)
+    } else if rule == "weekdays" {
+        let mut days = today + 1;
+        while weekday(days) >= 5 {
+            days += 1;
+        }
+        days
+    } else if let Some(wd) = parse_weekday(rule) {
+        next_weekday(today, wd)
+    } else {
+        // every date is on the day of the series, so a month keeps its day (31th, 29th, 31th)
+        let (rule, day) = match rule.split_once('@') {
+            Some((rule, day)) => (rule, day.parse::<u32>().ok()),
+            None => (rule, None),
+        };
+        let (n, unit) = split_interval(rule)?;
+        let first = (due.unwrap_or(start) / 86_400) as i64;
+        let day = day.unwrap_or(civil_from_days(first).2);
+        let next = |k: i64| match unit {
+            'm' => add_months_on(first, k * n, day),
+            'y' => add_months_on(first, 12 * k * n, day),
+            _ => add_interval(first, k * n, unit),
+        };
+        let mut k = 1;
+        while next(k) <= today {
+            k += 1;
+        }
+        next(k)
+    };
+
+    Some(days as u64 * 86_400)
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn day(y: i64, m: u32, d: u32) -> u64 {
+        days_from_civil(y, m, d) as u64 * 86_400
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_from_secs(0), (1970, 1, 1));
+        assert_eq!(civil_from_secs(day(2024, 2, 29) + 86_399), (2024, 2, 29));
+        assert_eq!(days_in_month(2024, 2), 29);
+        assert_eq!(days_in_month(2100, 2), 28);
+        assert_eq!(date_string(day(2024, 5, 1)), "2024-05-01");
+        assert_eq!(parse_date("2024-05-01"), Some(day(2024, 5, 1)));
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2024-5-1"), None);
+        assert_eq!(weekday(days_from_civil(2024, 5, 1)), 2);
+    }
+
+    #[test]
+    fn due_dates() {
+        // Wednesday, 2024-05-01 12:00
+        let now = day(2024, 5, 1) + 43_200;
+        assert_eq!(parse_due("today", now), Some(day(2024, 5, 1)));
+        assert_eq!(parse_due("Tomorrow", now), Some(day(2024, 5, 2)));
+        assert_eq!(parse_due("friday", now), Some(day(2024, 5, 3)));
+        assert_eq!(parse_due("next fri", now), Some(day(2024, 5, 3)));
+        assert_eq!(parse_due("wed", now), Some(day(2024, 5, 8)));
+        assert_eq!(parse_due("next week", now), Some(day(2024, 5, 6)));
+        assert_eq!(parse_due("eow", now), Some(day(2024, 5, 5)));
+        assert_eq!(parse_due("eom", now), Some(day(2024, 5, 31)));
+        assert_eq!(parse_due("eoy", now), Some(day(2024, 12, 31)));
+        assert_eq!(parse_due("in 3 days", now), Some(day(2024, 5, 4)));
+        assert_eq!(parse_due("in 1 day", now), Some(day(2024, 5, 2)));
+        assert_eq!(parse_due("in 2 weeks", now), Some(day(2024, 5, 15)));
+        assert_eq!(parse_due("in 1 month", now), Some(day(2024, 6, 1)));
+        assert_eq!(parse_due("in 1 month", day(2024, 1, 31)), Some(day(2024, 2, 29)));
+        assert_eq!(parse_due("2024-06-10", now), Some(day(2024, 6, 10)));
+        for invalid in ["", "fr", "someday", "in x days", "in 3 years", "2024-13-01"] {
+            assert_eq!(parse_due(invalid, now), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn date_formats() {
+        assert_eq!(date_format("%d.%m.%Y %H:%M"), "%d.%m.%Y");
+        assert_eq!(date_format("%H:%M"), "%Y-%m-%d");
+        assert_eq!(format_time(day(2024, 5, 1) + 3_661, "%d.%m.%Y %H:%M:%S"), "01.05.2024 01:01:01");
+    }
+
+    #[test]
+    fn recur_rules() {
+        assert_eq!(parse_recur("weekly").as_deref(), Some("1w"));
+        assert_eq!(parse_recur("Fri").as_deref(), Some("friday"));
+        assert_eq!(parse_recur("+3d").as_deref(), Some("+3d"));
+        assert_eq!(parse_recur("weekdays").as_deref(), Some("weekdays"));
+        assert_eq!(parse_recur("0d"), None);
+        assert_eq!(parse_recur("3x"), None);
+        assert_eq!(parse_recur("Monthly@31").as_deref(), Some("1m@31"));
+        assert_eq!(parse_recur("1y@29").as_deref(), Some("1y@29"));
+        for invalid in ["1w@31", "+1m@31", "1m@28", "1m@032", "1m@31@31", "friday@29"] {
+            assert_eq!(parse_recur(invalid), None, "{}", invalid);
+        }
+        assert_eq!(anchor_recur("1m", Some(day(2024, 1, 31)), 0), "1m@31");
+        assert_eq!(anchor_recur("1m@31", Some(day(2024, 5, 15)), 0), "1m");
+        assert_eq!(anchor_recur("2y", None, day(2024, 2, 29)), "2y@29");
+        assert_eq!(anchor_recur("1w", Some(day(2024, 1, 31)), 0), "1w");
+        assert_eq!(anchor_recur("+1m", Some(day(2024, 1, 31)), 0), "+1m");
+    }
+
+    #[test]
+    fn next_due_dates() {
+        let done = |y, m, d| day(y, m, d) + 43_200;
+        // the schedule of the due dates, the missed dates are skipped
+        assert_eq!(next_due("1w", Some(day(2024, 5, 1)), 0, done(2024, 5, 1)), Some(day(2024, 5, 8)));
+        assert_eq!(next_due("1w", Some(day(2024, 5, 1)), 0, done(2024, 5, 20)), Some(day(2024, 5, 22)));
+        assert_eq!(next_due("2d", None, day(2024, 5, 1), done(2024, 5, 1)), Some(day(2024, 5, 3)));
+        assert_eq!(next_due("1y", Some(day(2024, 2, 29)), 0, done(2024, 2, 29)), Some(day(2025, 2, 28)));
+        // the day of the series is the day of the due date without a day in the rule
+        assert_eq!(next_due("1m", Some(day(2024, 1, 31)), 0, done(2024, 1, 31)), Some(day(2024, 2, 29)));
+        assert_eq!(next_due("1m", Some(day(2024, 2, 29)), 0, done(2024, 2, 29)), Some(day(2024, 3, 29)));
+        assert_eq!(next_due("1m@31", Some(day(2024, 2, 29)), 0, done(2024, 2, 29)), Some(day(2024, 3, 31)));
+        assert_eq!(next_due("1m@31", Some(day(2024, 2, 29)), 0, done(2024, 5, 1)), Some(day(2024, 5, 31)));
+        // the time after the completion
+        assert_eq!(next_due("+3d", Some(day(2024, 5, 1)), 0, done(2024, 5, 20)), Some(day(2024, 5, 23)));
+        // Friday 2024-05-03
+        assert_eq!(next_due("weekdays", None, 0, done(2024, 5, 3)), Some(day(2024, 5, 6)));
+        assert_eq!(next_due("weekdays", None, 0, done(2024, 5, 6)), Some(day(2024, 5, 7)));
+        assert_eq!(next_due("friday", None, 0, done(2024, 5, 3)), Some(day(2024, 5, 10)));
+        assert_eq!(next_due("x", None, 0, done(2024, 5, 3)), None);
+    }
+
+    #[test]
+    fn monthly_series_keeps_its_day() {
+        let mut due = Some(day(2024, 1, 31));
+        let mut dates = Vec::new();
+        for _ in 0..5 {
+            due = next_due("1m@31", due, 0, due.unwrap());
+            dates.push(due.unwrap());
+        }
+        assert_eq!(dates, vec![day(2024, 2, 29), day(2024, 3, 31), day(2024, 4, 30), day(2024, 5, 31), day(2024, 6, 30)]);
+        let mut due = Some(day(2024, 2, 29));
+        for year in [2025, 2026, 2027] {
+            due = next_due("1y@29", due, 0, due.unwrap());
+            assert_eq!(due, Some(day(year, 2, 28)));
+        }
+        assert_eq!(next_due("1y@29", due, 0, due.unwrap()), Some(day(2028, 2, 29)));
+    }
+
+}
@@ -0,0 +1,1048 @@
+//! Export and import of the tasks in other formats.
+//!
+//! Every format has a pair of functions, ```export_*()``` creates the text of the
+//! whole list and ```import_*()``` reads the tasks back from such a text.
+//!
+//! An imported task without a permanent ID in the file has an empty ```uid```, so the import can
+//! tell the tasks of the file apart from the tasks with an ID, it gets a new ID in the list.
+
+use crate::todo_date::{civil_from_secs, date_string, days_from_civil, days_in_month, parse_date, parse_recur, weekday, WEEKDAYS};
+use crate::todo_json::{self, JsonValue};
+use crate::todo_lib::{is_tag, is_uid, since_string, split_tags, task_tree, time_now, TodoItem};
+use crate::todo_migrate::{check_version, migrate_item, migrate_json, migrate_list};
+use crate::todo_storage::{field_sessions, item_from_json, items_to_json, sessions_field, split_escaped, FORMAT_VERSION};
+
+/// Export all tasks as JSON, it's the same structure as the JSON storage backend uses, so an
+/// exported file can be used as database and vice versa.
+pub fn export_json(items: &[TodoItem]) -> String {
+    items_to_json(items).to_pretty()
+}
+
+/// Import tasks from JSON, an exported object or a plain array of tasks is accepted.
+pub fn import_json(text: &str) -> Result<Vec<TodoItem>, String> {
+
+    let json = todo_json::parse(text)?;
+
+    // a plain array of tasks has no format version, it's read as the current format
+    let mut version = FORMAT_VERSION;
+    let tasks = match &json {
+        JsonValue::Array(tasks) => tasks,
+        JsonValue::Object(_) => {
+            version = json.get("format").and_then(|v| v.as_u64()).unwrap_or(FORMAT_VERSION as u64).min(u32::MAX as u64) as u32;
+            check_version(version)?;
+            match json.get("tasks").and_then(|t| t.as_array()) {
+                Some(tasks) => tasks,
+                None => return Err(String::from("no tasks found")),
+            }
+        }
+        _ => return Err(String::from("expected an object or an array of tasks")),
+    };
+
+    let mut items: Vec<TodoItem> = Vec::new();
+    for (nr, t) in tasks.iter().enumerate() {
+        match item_from_json(&migrate_json(version, t)) {
+            Ok(mut ds) => {
+                migrate_item(version, &mut ds);
+                // the migration creates an ID for an old file, it's not an ID of the file
+                if t.get("uid").is_none() {
+                    ds.uid.clear();
+                }
+                items.push(ds);
+            }
+            Err(err) => return Err(format!("task {}: {}", nr + 1, err)),
+        }
+    }
+    migrate_list(version, &mut items.iter_mut().collect::<Vec<&mut TodoItem>>());
+    Ok(items)
+
+}
+
+/// Export all tasks in the todo.txt format, one task per line.
+pub fn export_todotxt(items: &[TodoItem]) -> String {
+    let mut s = String::new();
+    for ds in items {
+        s.push_str(item_to_todotxt(ds).as_str());
+        s.push('\n');
+    }
+    s
+}
+
+/// Import tasks from the todo.txt format, empty lines are skipped.
+pub fn import_todotxt(text: &str) -> Result<Vec<TodoItem>, String> {
+    let mut items: Vec<TodoItem> = Vec::new();
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut ds = item_from_todotxt(line);
+        if !line.contains(format!("id:{}", ds.uid).as_str()) {
+            ds.uid.clear();
+        }
+        ds.index = items.len() as u32 + 1;
+        items.push(ds);
+    }
+    Ok(items)
+}
+
+/// Convert a ```TodoItem``` into a todo.txt line:
+///
+/// ```x 2024-05-01 2024-04-20 (A) task +project @context due:2024-05-10 parent:5d2e... dep:0a7b...,9c3f... rec:1w id:6f1c2a9e-...```
+///
+/// The tag ```parent:``` is the permanent ID of the parent of a subtask, ```dep:``` are the
+/// permanent IDs of the tasks, which block the task (older files have the line numbers),
+/// ```rec:``` is the rule of a recurring task, ```id:``` is the
+/// permanent ID, ```time:``` are the tracked work sessions and ```note:``` are the notes, the
+/// spaces, line breaks and backslashes are escaped (```\s```, ```\n``` and ```\\```). The dates
+/// of todo.txt have no time, so ```created:``` and ```completed:``` are the exact times in seconds
+/// since the UNIX epoch, if they aren't midnight. A task done without a recorded time of
+/// completion uses the creation date as completion date. Line breaks in the task can't be stored
+/// in todo.txt, they are replaced by spaces.
+pub fn item_to_todotxt(ds: &TodoItem) -> String {
+
+    let mut task = ds.task.replace(['\r', '\n'], " ");
+    let prio = ds.priority.map(|p| format!("({}) ", p)).unwrap_or_default();
+    for tag in &ds.tags {
+        task.push(' ');
+        task.push_str(tag);
+    }
+    if let Some(due) = ds.due {
+        task.push_str(format!(" due:{}", date_string(due)).as_str());
+    }
+    if let Some(parent) = &ds.parent {
+        task.push_str(format!(" parent:{}", parent).as_str());
+    }
+    if !ds.depends.is_empty() {
+        task.push_str(format!(" dep:{}", ds.depends.join(",")).as_str());
+    }
+    if let Some(rule) = &ds.recur {
+        task.push_str(format!(" rec:{}", rule).as_str());
+    }
+    task.push_str(format!(" id:{}", ds.uid).as_str());
+    if !ds.sessions.is_empty() {
+        task.push_str(format!(" time:{}", sessions_field(&ds.sessions)).as_str());
+    }
+    if !ds.notes.is_empty() {
+        task.push_str(format!(" note:{}", todotxt_escape(&ds.notes)).as_str());
+    }
+    if !ds.start.is_multiple_of(86_400) {
+        task.push_str(format!(" created:{}", ds.start).as_str());
+    }
+    if let Some(completed) = ds.completed.filter(|c| ds.is_done && !c.is_multiple_of(86_400)) {
+        task.push_str(format!(" completed:{}", completed).as_str());
+    }
+
+    if ds.is_done {
+        format!("x {} {} {}{}", date_string(ds.completed.unwrap_or(ds.start)), date_string(ds.start), prio, task)
+    } else {
+        format!("{}{} {}", prio, date_string(ds.start), task)
+    }
+
+}
+
+/// Create a ```TodoItem``` from a todo.txt line. Projects and contexts are the tags of the task,
+/// other ```key:value``` tags are part of the task text, only the tags ```due:```, ```parent:```,
+/// ```dep:```, ```rec:```, ```id:```, ```time:```, ```note:```, ```created:``` and ```completed:```
+/// are read. A task without a creation date starts now.
+pub fn item_from_todotxt(line: &str) -> TodoItem {
+
+    let mut rest = line.trim();
+    let mut done = false;
+    let mut prio: Option<char> = None;
+    let mut start: Option<u64> = None;
+    let mut completed: Option<u64> = None;
+
+    if let Some(r) = rest.strip_prefix("x ") {
+        done = true;
+        rest = r.trim_start();
+        // the first date of a finished task is the completion date
+        if let Some(d) = first_word(rest).and_then(parse_date) {
+            rest = rest[10..].trim_start();
+            completed = Some(d);
+        }
+    } else if let Some(p) = split_priority(rest) {
+        prio = Some(p);
+        rest = rest[3..].trim_start();
+    }
+
+    if let Some(d) = first_word(rest).and_then(parse_date) {
+        rest = rest[10..].trim_start();
+        start = Some(d);
+    }
+
+    if done {
+        if let Some(p) = split_priority(rest) {
+            prio = Some(p);
+            rest = rest[3..].trim_start();
+        }
+    }
+
+    let (task, due) = split_value_tag(rest, "due:", parse_date);
+    let (task, parent) = split_value_tag(&task, "parent:", todotxt_link);
+    let (task, depends) = split_value_tag(&task, "dep:", |d| d.split(',').map(todotxt_link).collect::<Option<Vec<String>>>());
+    let (task, recur) = split_value_tag(&task, "rec:", parse_recur);
+    let (task, uid) = split_value_tag(&task, "id:", |u| Some(u.to_string()).filter(|u| is_uid(u)));
+    let (task, sessions) = split_value_tag(&task, "time:", |s| field_sessions(s).ok());
+    let (task, notes) = split_value_tag(&task, "note:", todotxt_unescape);
+    // the exact times are only used on the day of the dates, another tool could have changed them
+    let (task, created) = split_value_tag(&task, "created:", |t| t.parse::<u64>().ok());
+    let (task, completed_at) = split_value_tag(&task, "completed:", |t| t.parse::<u64>().ok());
+    let start = match (start, created) {
+        (Some(day), Some(t)) if t / 86_400 * 86_400 == day => Some(t),
+        _ => start,
+    };
+    let completed = match (completed, completed_at) {
+        (Some(day), Some(t)) if t / 86_400 * 86_400 == day => Some(t),
+        _ => completed,
+    };
+    let (task, tags) = split_tags(&task);
+    // a finished task with only one date was started at the completion date
+    let mut ds = TodoItem::from(0, task, start.or(completed).unwrap_or_else(time_now), done);
+    ds.priority = prio;
+    ds.due = due;
+    ds.tags = tags;
+    ds.completed = completed;
+    ds.parent = parent;
+    ds.depends = depends.unwrap_or_default();
+    ds.recur = recur;
+    if let Some(uid) = uid {
+        ds.uid = uid;
+    }
+    ds.sessions = sessions.unwrap_or_default();
+    ds.notes = notes.unwrap_or_default();
+    ds
+
+}
+
+/// For internal use.
+///
+/// Read a link to another task, the permanent ID or the line of the task like in older files.
+fn todotxt_link(link: &str) -> Option<String> {
+    Some(link.to_string()).filter(|l| is_uid(l) || l.parse::<u32>().is_ok_and(|nr| nr > 0))
+}
+
+/// For internal use.
+///
+/// The priority of a todo.txt task, ```(A) ``` to ```(Z) ``` at the start.
+pub fn split_priority(s: &str) -> Option<char> {
+    let b = s.as_bytes();
+    if b.len() >= 4 && b[0] == b'(' && b[1].is_ascii_uppercase() && b[2] == b')' && b[3] == b' ' {
+        return Some(b[1] as char);
+    }
+    None
+}
+
+/// For internal use.
+///
+/// Remove a tag like ```due:2024-05-10``` from a task, the result is the task and the value of
+/// the tag. A tag with an invalid value is kept in the task.
+fn split_value_tag<T>(task: &str, key: &str, parse: impl Fn(&str) -> Option<T>) -> (String, Option<T>) {
+    let mut value = None;
+    let words: Vec<&str> = task.split(' ').filter(|w| {
+        match w.strip_prefix(key).and_then(&parse) {
+            Some(v) if value.is_none() => {
+                value = Some(v);
+                false
+            }
+            _ => true,
+        }
+    }).collect();
+    (words.join(" "), value)
+}
+
+/// For internal use.
+///
+/// Escape the value of a todo.txt tag, it can't have spaces or line breaks.
+fn todotxt_escape(value: &str) -> String {
+    let mut s = String::new();
+    for c in value.chars() {
+        match c {
+            '\\' => s.push_str("\\\\"),
+            ' ' => s.push_str("\\s"),
+            '\t' => s.push_str("\\t"),
+            '\n' => s.push_str("\\n"),
+            '\r' => {}
+            c => s.push(c),
+        }
+    }
+    s
+}
+
+/// For internal use.
+///
+/// Read an escaped value of a todo.txt tag, the counterpart of ```todotxt_escape()```, an unknown
+/// escape is an invalid value.
+fn todotxt_unescape(value: &str) -> Option<String> {
+    let mut s = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            s.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => s.push('\\'),
+            's' => s.push(' '),
+            't' => s.push('\t'),
+            'n' => s.push('\n'),
+            _ => return None,
+        }
+    }
+    Some(s)
+}
+
+/// For internal use.
+fn first_word(s: &str) -> Option<&str> {
+    s.split(' ').next()
+}
+
+/// Export all tasks as a GitHub flavoured Markdown checklist, the open tasks show their age.
+///
+/// ```- [ ] (A) task +project due:2024-05-10 (since 3 day(s))``` or ```- [x] task (done 2024-05-01)```
+///
+/// The subtasks are indented under their parent and the notes are a quote under the task, the
+/// dependencies and the tracked time of the tasks are not exported.
+pub fn export_markdown(items: &[TodoItem]) -> String {
+    let mut s = String::new();
+    let items: Vec<&TodoItem> = items.iter().collect();
+    for (ds, depth) in task_tree(&items) {
+        s.push_str("  ".repeat(depth).as_str());
+        let prio = ds.priority.map(|p| format!("({}) ", p)).unwrap_or_default();
+        let mut task = prio + ds.task.replace(['\r', '\n'], " ").as_str();
+        for tag in &ds.tags {
+            task.push(' ');
+            task.push_str(tag);
+        }
+        if let Some(due) = ds.due {
+            task.push_str(format!(" due:{}", date_string(due)).as_str());
+        }
+        if let Some(completed) = ds.completed.filter(|_| ds.is_done) {
+            s.push_str(format!("- [x] {} (done {})\n", task, date_string(completed)).as_str());
+        } else if ds.is_done {
+            s.push_str(format!("- [x] {}\n", task).as_str());
+        } else {
+            s.push_str(format!("- [ ] {} {}\n", task, since_string(ds.start)).as_str());
+        }
+        for line in ds.notes.lines() {
+            s.push_str(format!("{}  > {}", "  ".repeat(depth), line).trim_end());
+            s.push('\n');
+        }
+    }
+    s
+}
+
+/// Import tasks from a Markdown checklist, all other lines of the text are ignored. The age of an
+/// exported task is used to calculate the start time, an indented task is a subtask and an
+/// indented quote are the notes of the task.
+pub fn import_markdown(text: &str) -> Result<Vec<TodoItem>, String> {
+
+    let mut items: Vec<TodoItem> = Vec::new();
+    // the indentation and the ID of the possible parents of the next task, the tasks of the file
+    // have no permanent ID, so a subtask links the number of its parent
+    let mut parents: Vec<(usize, u32)> = Vec::new();
+    for line in text.lines() {
+
+        let l = line.trim_start();
+        let indent = line.len() - l.len();
+        if let Some(note) = l.strip_prefix('>').filter(|_| parents.last().is_some_and(|(i, _)| indent > *i)) {
+            let ds = items.last_mut().unwrap();
+            if !ds.notes.is_empty() {
+                ds.notes.push('\n');
+            }
+            ds.notes.push_str(note.strip_prefix(' ').unwrap_or(note));
+            continue;
+        }
+        let l = match l.strip_prefix("- ").or(l.strip_prefix("* ")).or(l.strip_prefix("+ ")) {
+            Some(l) => l,
+            None => continue,
+        };
+        let (done, task) = match l.get(..4) {
+            Some("[ ] ") => (false, &l[4..]),
+            Some("[x] ") | Some("[X] ") => (true, &l[4..]),
+            _ => continue,
+        };
+
+        let (task, completed) = split_done(task.trim());
+        let (mut task, start) = split_since(task);
+        let priority = split_priority(task);
+        if priority.is_some() {
+            task = task[4..].trim_start();
+        }
+        if task.is_empty() {
+            continue;
+        }
+
+        let (task, due) = split_value_tag(task, "due:", parse_date);
+        let (task, tags) = split_tags(&task);
+        let mut ds = TodoItem::from(items.len() as u32 + 1, task, start, done);
+        while parents.last().is_some_and(|(i, _)| *i >= indent) {
+            parents.pop();
+        }
+        ds.parent = parents.last().map(|(_, p)| p.to_string());
+        parents.push((indent, ds.index));
+        ds.priority = priority;
+        ds.due = due;
+        ds.tags = tags;
+        ds.completed = completed.filter(|_| done);
+        ds.uid.clear();
+        items.push(ds);
+
+    }
+    Ok(items)
+
+}
+
+/// For internal use.
+///
+/// Split the age ```(since 3 day(s))``` from the end of a task, the result is the task and the
+/// start time calculated from the age (or now, if the task has no age).
+fn split_since(task: &str) -> (&str, u64) {
+
+    let now = time_now();
+    let pos = match task.rfind("(since ") {
+        Some(pos) if task.ends_with("(s))") => pos,
+        _ => return (task, now),
+    };
+
+    let age: Vec<&str> = task[pos + 7..].split(' ').collect();
+    let n = match age.first().and_then(|n| n.parse::<u64>().ok()) {
+        Some(n) => n,
+        None => return (task, now),
+    };
+    let unit: u64 = match age.get(1).copied() {
+        Some("second(s))") => 1,
+        Some("minute(s))") => 60,
+        Some("hour(s))") => 3_600,
+        Some("day(s))") => 86_400,
+        _ => return (task, now),
+    };
+
+    (task[..pos].trim_end(), now.saturating_sub(n * unit))
+
+}
+
+/// For internal use.
+///
+/// Split the completion date ```(done 2024-05-01)``` from the end of a task.
+fn split_done(task: &str) -> (&str, Option<u64>) {
+    match task.strip_suffix(')').and_then(|t| t.rsplit_once("(done ")) {
+        Some((t, date)) => match parse_date(date) {
+            Some(d) => (t.trim_end(), Some(d)),
+            None => (task, None),
+        },
+        None => (task, None),
+    }
+}
+
+/// Export all tasks as iCalendar file (RFC 5545), every task is a ```VTODO``` component.
+///
+/// The ```UID``` is the permanent ID of the task, so it stays the same for every export and a
+/// calendar client can update its tasks. A subtask is ```RELATED-TO``` the ```UID``` of its parent,
+/// a blocked task ```DEPENDS-ON``` the blocking tasks (RFC 9253). The rule of a recurring task is
+/// an ```RRULE```, a recurrence after the completion (```+3d```) can't be exported. The notes are
+/// the ```DESCRIPTION```. All times are UTC DATE-TIME values, the ```DUE``` is the end of the day.
+pub fn export_ical(items: &[TodoItem]) -> String {
+
+    let mut s = String::new();
+    let now = ical_datetime(time_now());
+
+    push_ical_line(&mut s, "BEGIN:VCALENDAR");
+    push_ical_line(&mut s, "VERSION:2.0");
+    push_ical_line(&mut s, format!("PRODID:-//r_todo//todo {}//EN", env!("CARGO_PKG_VERSION")).as_str());
+
+    for ds in items {
+        push_ical_line(&mut s, "BEGIN:VTODO");
+        push_ical_line(&mut s, format!("UID:{}", ds.uid).as_str());
+        push_ical_line(&mut s, format!("DTSTAMP:{}", now).as_str());
+        push_ical_line(&mut s, format!("DTSTART:{}", ical_datetime(ds.start)).as_str());
+        push_ical_line(&mut s, format!("SUMMARY:{}", ical_escape(&ds.task)).as_str());
+        if !ds.notes.is_empty() {
+            push_ical_line(&mut s, format!("DESCRIPTION:{}", ical_escape(&ds.notes)).as_str());
+        }
+        if let Some(p) = ds.priority {
+            push_ical_line(&mut s, format!("PRIORITY:{}", ical_priority(p)).as_str());
+        }
+        if let Some(due) = ds.due {
+            push_ical_line(&mut s, format!("DUE:{}", ical_datetime(due + 86_399)).as_str());
+        }
+        if !ds.tags.is_empty() {
+            let tags: Vec<String> = ds.tags.iter().map(|t| ical_escape(t)).collect();
+            push_ical_line(&mut s, format!("CATEGORIES:{}", tags.join(",")).as_str());
+        }
+        if let Some(parent) = &ds.parent {
+            push_ical_line(&mut s, format!("RELATED-TO;RELTYPE=PARENT:{}", parent).as_str());
+        }
+        for blocker in &ds.depends {
+            push_ical_line(&mut s, format!("RELATED-TO;RELTYPE=DEPENDS-ON:{}", blocker).as_str());
+        }
+        if let Some(rrule) = ds.recur.as_deref().and_then(ical_rrule) {
+            push_ical_line(&mut s, format!("RRULE:{}", rrule).as_str());
+        }
+        if ds.is_done {
+            push_ical_line(&mut s, "STATUS:COMPLETED");
+            if let Some(completed) = ds.completed {
+                push_ical_line(&mut s, format!("COMPLETED:{}", ical_datetime(completed)).as_str());
+            }
+        } else {
+            push_ical_line(&mut s, "STATUS:NEEDS-ACTION");
+        }
+        push_ical_line(&mut s, "END:VTODO");
+    }
+
+    push_ical_line(&mut s, "END:VCALENDAR");
+    s
+
+}
+
+/// Import the ```VTODO``` components of an iCalendar file, all other components are ignored.
+/// A task without ```DTSTART``` starts now, a task with the ```STATUS:COMPLETED``` or a
+/// ```COMPLETED``` time is done. The ```CATEGORIES``` are the tags, a category without ```+``` or
+/// ```@``` is a project. A task ```RELATED-TO``` the ```UID``` of a parent is a subtask, one which
+/// ```DEPENDS-ON``` another task is blocked by it. A simple ```RRULE``` is the rule of a recurring
+/// task, other rules are ignored. The ```DESCRIPTION``` are the notes, a ```UID``` of todo is kept
+/// as permanent ID. The properties of a component inside a task, e.g. a ```VALARM```, are skipped.
+///
+/// A time with ```Z``` is UTC, a time with a ```TZID``` is converted with the ```VTIMEZONE``` of
+/// the file. A floating time without a time zone is read as UTC, like todo shows all times.
+pub fn import_ical(text: &str) -> Result<Vec<TodoItem>, String> {
+
+    // unfold the lines, a line starting with a space or tab continues the line before
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix(' ').or(line.strip_prefix('\t')), lines.last_mut()) {
+            (Some(cont), Some(last)) => last.push_str(cont),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let zones = ical_zones(&lines);
+    let mut items: Vec<TodoItem> = Vec::new();
+    let mut current: Option<TodoItem> = None;
+    // the components inside the current task, e.g. BEGIN:VALARM
+    let mut nested: Vec<String> = Vec::new();
+    // the UID, the UID of the parent and the UIDs of the blocking tasks of every task, the related
+    // tasks are found after all tasks are read
+    let mut uids: Vec<(String, Option<String>, Vec<String>)> = Vec::new();
+    let mut uid = (String::new(), None, Vec::new());
+    for (nr, line) in lines.iter().enumerate() {
+
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name, value),
+            None => continue,
+        };
+        // the parameters like DTSTART;VALUE=DATE:20240501 are not needed, only the type of a relation
+        let reltype = name.to_ascii_uppercase().split(';').find_map(|p| p.strip_prefix("RELTYPE=").map(String::from));
+        let reltype = reltype.unwrap_or(String::from("PARENT"));
+        let tzid = name.split(';').skip(1).find_map(|p| p.split_once('=').filter(|(k, _)| k.eq_ignore_ascii_case("TZID")));
+        let tzid = tzid.map(|(_, id)| id.trim_matches('"'));
+        let name = name.split(';').next().unwrap_or_default().to_ascii_uppercase();
+
+        if current.is_some() && (name == "BEGIN" || !nested.is_empty()) {
+            if name == "BEGIN" {
+                nested.push(value.trim().to_ascii_uppercase());
+            } else if name == "END" && nested.last().is_some_and(|n| value.trim().eq_ignore_ascii_case(n)) {
+                nested.pop();
+            }
+            continue;
+        }
+
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VTODO") => {
+                let mut ds = TodoItem::from(0, String::new(), time_now(), false);
+                ds.uid.clear();
+                current = Some(ds);
+            }
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VTODO") => {
+                let mut ds = current.take().unwrap();
+                ds.index = items.len() as u32 + 1;
+                if !ds.is_done {
+                    ds.completed = None;
+                }
+                items.push(ds);
+                uids.push(std::mem::take(&mut uid));
+            }
+            ("UID", Some(ds)) => {
+                uid.0 = value.to_string();
+                if is_uid(&value.to_ascii_lowercase()) {
+                    ds.uid = value.to_ascii_lowercase();
+                }
+            }
+            ("RELATED-TO", Some(_)) if reltype == "PARENT" => uid.1 = Some(value.to_string()),
+            ("RELATED-TO", Some(_)) if reltype == "DEPENDS-ON" => uid.2.push(value.to_string()),
+            ("RRULE", Some(ds)) => ds.recur = parse_ical_rrule(value),
+            ("SUMMARY", Some(ds)) => ds.task = ical_unescape(value),
+            ("DESCRIPTION", Some(ds)) => ds.notes = ical_unescape(value),
+            ("DTSTART", Some(ds)) => {
+                ds.start = match ical_time(value, tzid, &zones) {
+                    Some(start) => start,
+                    None => return Err(format!("line {}: invalid date '{}'", nr + 1, value)),
+                };
+            }
+            ("DUE", Some(ds)) => {
+                ds.due = match parse_ical_datetime(value) {
+                    Some(due) => Some(due / 86_400 * 86_400),
+                    None => return Err(format!("line {}: invalid date '{}'", nr + 1, value)),
+                };
+            }
+            ("STATUS", Some(ds)) => ds.is_done = value.eq_ignore_ascii_case("COMPLETED"),
+            ("PRIORITY", Some(ds)) => {
+                ds.priority = match value.trim().parse::<u8>() {
+                    Ok(0) => None,
+                    Ok(p @ 1..=9) => Some((b'A' + p - 1) as char),
+                    _ => return Err(format!("line {}: invalid priority '{}'", nr + 1, value)),
+                };
+            }
+            ("COMPLETED", Some(ds)) => {
+                ds.is_done = true;
+                ds.completed = match ical_time(value, tzid, &zones) {
+                    Some(completed) => Some(completed),
+                    None => return Err(format!("line {}: invalid date '{}'", nr + 1, value)),
+                };
+            }
+            ("CATEGORIES", Some(ds)) => {
+                for category in split_escaped(value, ',') {
+                    let category = category.trim().replace(char::is_whitespace, "-");
+                    let tag = if is_tag(&category) { category } else { format!("+{}", category) };
+                    if is_tag(&tag) && !ds.tags.contains(&tag) {
+                        ds.tags.push(tag);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+    }
+
+    if current.is_some() {
+        return Err(String::from("missing END:VTODO"));
+    }
+    // a UID of another calendar isn't a permanent ID of todo, so the tasks are linked by their number
+    let index = |uid: &String| uids.iter().position(|(u, _, _)| u == uid && !u.is_empty()).map(|p| (p + 1).to_string());
+    for (i, (_, parent, depends)) in uids.iter().enumerate() {
+        items[i].parent = parent.as_ref().and_then(index);
+        items[i].depends = depends.iter().filter_map(index).collect();
+    }
+    Ok(items)
+
+}
+
+/// For internal use.
+///
+/// A change of the offset of a ```VTIMEZONE```, a ```STANDARD``` or ```DAYLIGHT``` has the local
+/// time of the first change, the offset to UTC in seconds and the yearly rule (the month, the
+/// week of the month and the weekday, e.g. ```BYMONTH=3;BYDAY=-1SU```).
+type IcalChange = (u64, i64, Option<(u32, i32, u32)>);
+
+/// For internal use.
+///
+/// All changes of the offset of a time zone.
+type IcalZone = Vec<IcalChange>;
+
+/// For internal use.
+///
+/// Read the time zones of an iCalendar file with their ```TZID```.
+fn ical_zones(lines: &[String]) -> Vec<(String, IcalZone)> {
+    let mut zones: Vec<(String, IcalZone)> = Vec::new();
+    let mut zone: Option<(String, IcalZone)> = None;
+    let mut change: Option<IcalChange> = None;
+    for line in lines {
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name.split(';').next().unwrap_or_default().to_ascii_uppercase(), value.trim()),
+            None => continue,
+        };
+        let observance = value.eq_ignore_ascii_case("STANDARD") || value.eq_ignore_ascii_case("DAYLIGHT");
+        match (name.as_str(), zone.as_mut(), change.as_mut()) {
+            ("BEGIN", None, _) if value.eq_ignore_ascii_case("VTIMEZONE") => zone = Some((String::new(), Vec::new())),
+            ("BEGIN", Some(_), None) if observance => change = Some((0, 0, None)),
+            ("END", Some(z), Some(_)) if observance => z.1.push(change.take().unwrap()),
+            ("END", Some(_), None) if value.eq_ignore_ascii_case("VTIMEZONE") => zones.push(zone.take().unwrap()),
+            ("TZID", Some(z), None) => z.0 = value.to_string(),
+            ("DTSTART", _, Some(c)) => c.0 = parse_ical_datetime(value).unwrap_or_default(),
+            ("TZOFFSETTO", _, Some(c)) => c.1 = parse_ical_offset(value).unwrap_or_default(),
+            ("RRULE", _, Some(c)) => c.2 = parse_ical_yearly(value),
+            _ => {}
+        }
+    }
+    zones
+}
+
+/// For internal use.
+///
+/// Read an offset to UTC like ```+0200``` or ```-0530```, the result are seconds.
+fn parse_ical_offset(value: &str) -> Option<i64> {
+    let (sign, hms) = match (value.get(..1), value.get(1..)) {
+        (Some("+"), Some(hms)) => (1, hms),
+        (Some("-"), Some(hms)) => (-1, hms),
+        _ => return None,
+    };
+    if (hms.len() != 4 && hms.len() != 6) || !hms.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let secs = hms[..2].parse::<i64>().ok()? * 3_600 + hms[2..4].parse::<i64>().ok()? * 60;
+    Some(sign * (secs + hms[4..].parse::<i64>().unwrap_or(0)))
+}
+
+/// For internal use.
+///
+/// Read the yearly rule of a time zone change, e.g. ```FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU```.
+fn parse_ical_yearly(value: &str) -> Option<(u32, i32, u32)> {
+    let part = |name: &str| value.split(';').find_map(|p| p.strip_prefix(name)).map(|v| v.to_ascii_uppercase());
+    if part("FREQ=")? != "YEARLY" {
+        return None;
+    }
+    let month = part("BYMONTH=")?.parse::<u32>().ok().filter(|m| (1..=12).contains(m))?;
+    let byday = part("BYDAY=")?;
+    let split = byday.len().checked_sub(2)?;
+    let day = WEEKDAYS.iter().position(|d| byday.get(split..).is_some_and(|b| d[..2].eq_ignore_ascii_case(b)))?;
+    let week = byday.get(..split)?.trim_start_matches('+').parse::<i32>().ok().filter(|w| *w != 0 && w.abs() <= 5)?;
+    Some((month, week, day as u32))
+}
+
+/// For internal use.
+///
+/// The offset to UTC of a local time, it's the offset of the last change before the time. A
+/// change with a yearly rule is repeated in every year after its first change.
+fn ical_zone_offset(zone: &IcalZone, local: u64) -> Option<i64> {
+    let (year, _, _) = civil_from_secs(local);
+    let mut last: Option<(u64, i64)> = None;
+    for &(start, offset, rule) in zone {
+        for y in [year - 1, year] {
+            let at = match rule {
+                Some((month, week, day)) if y >= 1970 => {
+                    let days = if week > 0 {
+                        let first = days_from_civil(y, month, 1);
+                        first + (day as i64 - weekday(first) as i64).rem_euclid(7) + (week as i64 - 1) * 7
+                    } else {
+                        let end = days_from_civil(y, month, days_in_month(y, month));
+                        end - (weekday(end) as i64 - day as i64).rem_euclid(7) + (week as i64 + 1) * 7
+                    };
+                    days as u64 * 86_400 + start % 86_400
+                }
+                None if y == year => start,
+                _ => continue,
+            };
+            if at >= start && at <= local && last.is_none_or(|(l, _)| at > l) {
+                last = Some((at, offset));
+            }
+        }
+    }
+    last.map(|(_, offset)| offset).or(zone.first().map(|c| c.1))
+}
+
+/// For internal use.
+///
+/// Read a DATE-TIME value as UTC, a local time with a ```TZID``` is converted with the time zones
+/// of the file. A DATE, a floating time and a time of an unknown time zone are read as UTC.
+fn ical_time(value: &str, tzid: Option<&str>, zones: &[(String, IcalZone)]) -> Option<u64> {
+    let local = parse_ical_datetime(value)?;
+    if value.trim().ends_with('Z') {
+        return Some(local);
+    }
+    let zone = tzid.and_then(|id| zones.iter().find(|(z, _)| z == id));
+    let offset = zone.and_then(|(_, zone)| ical_zone_offset(zone, local)).unwrap_or(0);
+    local.checked_add_signed(-offset)
+}
+
+/// For internal use.
+///
+/// Add a content line, lines longer than 75 octets are folded and every line ends with CRLF.
+fn push_ical_line(s: &mut String, line: &str) {
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            s.push_str("\r\n ");
+            len = 1;
+        }
+        s.push(c);
+        len += c.len_utf8();
+    }
+    s.push_str("\r\n");
+}
+
+/// For internal use.
+///
+/// The iCalendar priority from ```1``` (the highest) to ```9```, the priorities after ```I```
+/// are all ```9```.
+fn ical_priority(p: char) -> u8 {
+    (p as u8).saturating_sub(b'A').min(8) + 1
+}
+
+/// For internal use.
+///
+/// The ```RRULE``` of the rule of a recurring task, e.g. ```FREQ=WEEKLY;INTERVAL=2``` for ```2w```.
+/// The day of a monthly series isn't exported, it's the day of the due date again after an import.
+fn ical_rrule(rule: &str) -> Option<String> {
+    let rule = rule.split_once('@').map(|(r, _)| r).unwrap_or(rule);
+    if rule == "weekdays" {
+        return Some(String::from("FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR"));
+    }
+    if rule.starts_with('+') {
+        return None;
+    }
+    if !rule.starts_with(|c: char| c.is_ascii_digit()) {
+        // a weekday like friday
+        return Some(format!("FREQ=WEEKLY;BYDAY={}", rule[..2].to_ascii_uppercase()));
+    }
+    let (n, unit) = rule.split_at(rule.len() - 1);
+    let freq = match unit {
+        "d" => "DAILY",
+        "w" => "WEEKLY",
+        "m" => "MONTHLY",
+        _ => "YEARLY",
+    };
+    Some(format!("FREQ={};INTERVAL={}", freq, n))
+}
+
+/// For internal use.
+///
+/// Read a simple ```RRULE```, this is the counterpart of ```ical_rrule()```.
+fn parse_ical_rrule(value: &str) -> Option<String> {
+    let part = |name: &str| value.split(';').find_map(|p| p.strip_prefix(name)).map(|v| v.to_ascii_uppercase());
+    let interval = part("INTERVAL=").unwrap_or(String::from("1"));
+    match (part("FREQ=")?.as_str(), part("BYDAY=")) {
+        ("WEEKLY", Some(days)) if days == "MO,TU,WE,TH,FR" => Some(String::from("weekdays")),
+        ("WEEKLY", Some(day)) if interval == "1" => parse_recur(WEEKDAYS.iter().find(|d| d[..2].eq_ignore_ascii_case(&day))?),
+        (_, Some(_)) => None,
+        ("DAILY", None) => parse_recur(format!("{}d", interval).as_str()),
+        ("WEEKLY", None) => parse_recur(format!("{}w", interval).as_str()),
+        ("MONTHLY", None) => parse_recur(format!("{}m", interval).as_str()),
+        ("YEARLY", None) => parse_recur(format!("{}y", interval).as_str()),
+        _ => None,
+    }
+}
+
+/// For internal use.
+///
+/// Escape a TEXT value, see RFC 5545 section 3.3.11.
+fn ical_escape(text: &str) -> String {
+    let mut s = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => s.push_str("\\\\"),
+            ';' => s.push_str("\\;"),
+            ',' => s.push_str("\\,"),
+            '\n' => s.push_str("\\n"),
+            '\r' => {}
+            _ => s.push(c),
+        }
+    }
+    s
+}
+
+/// For internal use.
+///
+/// Remove the escaping of a TEXT value.
+fn ical_unescape(text: &str) -> String {
+    let mut s = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => s.push('\n'),
+                Some(e) => s.push(e),
+                None => s.push('\\'),
+            }
+        } else {
+            s.push(c);
+        }
+    }
+    s
+}
+
+/// For internal use.
+///
+/// The time as UTC DATE-TIME value, e.g. ```20240501T120000Z```.
+fn ical_datetime(secs: u64) -> String {
+    let (y, m, d) = civil_from_secs(secs);
+    let t = secs % 86_400;
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}Z", y, m, d, t / 3_600, t % 3_600 / 60, t % 60)
+}
+
+/// For internal use.
+///
+/// Parse a DATE (```20240501```) or DATE-TIME (```20240501T120000Z```) value, a time without
+/// ```Z``` is handled as UTC.
+fn parse_ical_datetime(value: &str) -> Option<u64> {
+    let value = value.trim().trim_end_matches('Z');
+    let (date, time) = value.split_once('T').unwrap_or((value, "000000"));
+    if date.len() != 8 || time.len() != 6 || !date.chars().chain(time.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let day = parse_date(format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..]).as_str())?;
+    let h = time[..2].parse::<u64>().ok()?;
+    let m = time[2..4].parse::<u64>().ok()?;
+    let s = time[4..].parse::<u64>().ok()?;
+    Some(day + h * 3_600 + m * 60 + s)
+}
+
+/// Export a table as CSV (RFC 4180), e.g. the report of the tracked time for a timesheet. A field
+/// with a comma, a quote or a line break is quoted.
+pub fn export_csv(header: &[&str], rows: &[Vec<String>]) -> String {
+    let quote = |field: &str| {
+        if field.contains([',', '"', '\r', '\n']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    };
+    let mut s = header.join(",");
+    s.push_str("\r\n");
+    for row in rows {
+        s.push_str(row.iter().map(|f| quote(f)).collect::<Vec<String>>().join(",").as_str());
+        s.push_str("\r\n");
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::todo_lib::new_uid;
+
+    fn day(y: i64, m: u32, d: u32) -> u64 {
+        days_from_civil(y, m, d) as u64 * 86_400
+    }
+
+    #[test]
+    fn todotxt_lines() {
+        let ds = item_from_todotxt("x 2024-05-02 2024-05-01 (A) call mom +family @phone due:2024-05-10 key:value");
+        assert!(ds.is_done);
+        assert_eq!(ds.task, "call mom key:value");
+        assert_eq!(ds.priority, Some('A'));
+        assert_eq!(ds.tags, vec!["+family", "@phone"]);
+        assert_eq!(ds.start, day(2024, 5, 1));
+        assert_eq!(ds.completed, Some(day(2024, 5, 2)));
+        assert_eq!(ds.due, Some(day(2024, 5, 10)));
+
+        let ds = item_from_todotxt("(B) 2024-05-01 open task rec:1w dep:2,3 parent:4 id:not-an-id");
+        assert!(!ds.is_done);
+        assert_eq!(ds.task, "open task id:not-an-id");
+        assert_eq!(ds.priority, Some('B'));
+        assert_eq!(ds.start, day(2024, 5, 1));
+        assert_eq!(ds.recur.as_deref(), Some("1w"));
+        // the line numbers of an older file are replaced by the permanent IDs after all lines are read
+        assert_eq!(ds.depends, vec!["2", "3"]);
+        assert_eq!(ds.parent.as_deref(), Some("4"));
+
+        // a finished task with only one date, a lowercase priority is text
+        let ds = item_from_todotxt("x 2024-05-02 (a) done");
+        assert_eq!(ds.task, "(a) done");
+        assert_eq!((ds.start, ds.completed, ds.priority), (day(2024, 5, 2), Some(day(2024, 5, 2)), None));
+
+        let ds = item_from_todotxt("no date due:tomorrow");
+        assert_eq!(ds.task, "no date due:tomorrow");
+        assert!(ds.start > day(2024, 5, 1) && ds.due.is_none());
+    }
+
+    #[test]
+    fn todotxt_round_trip() {
+        let mut ds = TodoItem::from(1, String::from("write report"), day(2024, 5, 1) + 3_661, true);
+        ds.completed = Some(day(2024, 5, 3) + 60);
+        ds.notes = String::from("a note\nwith\tlines \\s and spaces");
+        ds.sessions = vec![(day(2024, 5, 1) + 3_700, Some(day(2024, 5, 1) + 7_300))];
+        ds.tags = vec![String::from("+work")];
+        ds.parent = Some(new_uid());
+        ds.depends = vec![new_uid(), new_uid()];
+        let line = item_to_todotxt(&ds);
+        assert!(!line.contains('\n'));
+        let item = item_from_todotxt(&line);
+        assert_eq!(item.task, ds.task);
+        assert_eq!(item.notes, ds.notes);
+        assert_eq!(item.sessions, ds.sessions);
+        assert_eq!((&item.parent, &item.depends), (&ds.parent, &ds.depends));
+        assert_eq!((item.start, item.completed), (ds.start, ds.completed));
+        assert_eq!(item.uid, ds.uid);
+        assert_eq!(item_to_todotxt(&item), line);
+        // an exact time of another day is ignored, e.g. the date was changed by another tool
+        let item = item_from_todotxt(&line.replace("2024-05-01", "2024-05-02"));
+        assert_eq!(item.start, day(2024, 5, 2));
+    }
+
+    #[test]
+    fn markdown_round_trip() {
+        let mut ds = TodoItem::from(1, String::from("parent"), day(2024, 5, 1), false);
+        ds.tags = vec![String::from("+home")];
+        let mut sub = TodoItem::from(2, String::from("child"), day(2024, 5, 1), true);
+        sub.parent = Some(ds.uid.clone());
+        sub.notes = String::from("first line\n\n- not a task");
+        let items = import_markdown(&export_markdown(&[ds, sub])).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].notes, "first line\n\n- not a task");
+        assert_eq!((items[0].task.as_str(), &items[0].tags), ("parent", &vec![String::from("+home")]));
+        // Markdown has no permanent IDs, the subtask links the number of its parent in the file
+        assert_eq!((items[1].task.as_str(), items[1].is_done, items[1].parent.as_deref()), ("child", true, Some("1")));
+    }
+
+    #[test]
+    fn ical_folding() {
+        let line = format!("SUMMARY:{}", "äb€".repeat(40));
+        let mut s = String::new();
+        push_ical_line(&mut s, &line);
+        let lines: Vec<&str> = s.strip_suffix("\r\n").unwrap().split("\r\n").collect();
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|l| l.len() <= 75));
+        assert!(lines[1..].iter().all(|l| l.starts_with(' ')));
+        let unfolded: String = lines.iter().enumerate().map(|(i, l)| if i == 0 { *l } else { &l[1..] }).collect();
+        assert_eq!(unfolded, line);
+        let mut s = String::new();
+        push_ical_line(&mut s, "SUMMARY:short");
+        assert_eq!(s, "SUMMARY:short\r\n");
+    }
+
+    #[test]
+    fn ical_round_trip() {
+        let mut ds = TodoItem::from(1, format!("a long task, with; special characters {}", "ü".repeat(60)), day(2024, 5, 1) + 3_600, true);
+        ds.notes = String::from("first line\nsecond, line; with \\ backslash");
+        ds.tags = vec![String::from("+work"), String::from("@office")];
+        ds.priority = Some('A');
+        ds.due = Some(day(2024, 5, 10));
+        ds.completed = Some(day(2024, 5, 2) + 7_200);
+        ds.recur = Some(String::from("2w"));
+        let mut sub = TodoItem::from(2, String::from("subtask"), day(2024, 5, 1), false);
+        sub.parent = Some(ds.uid.clone());
+        sub.depends = vec![ds.uid.clone()];
+        let items = import_ical(&export_ical(&[ds.clone(), sub.clone()])).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].task, ds.task);
+        assert_eq!(items[0].notes, ds.notes);
+        assert_eq!(items[0].tags, ds.tags);
+        assert_eq!(items[0].priority, ds.priority);
+        assert_eq!((items[0].start, items[0].due, items[0].completed), (ds.start, ds.due, ds.completed));
+        assert_eq!(items[0].recur, ds.recur);
+        assert_eq!(items[0].uid, ds.uid);
+        assert!(items[0].is_done && !items[1].is_done);
+        assert_eq!((items[1].parent.as_deref(), &items[1].depends), (Some("1"), &vec![String::from("1")]));
+    }
+
+    #[test]
+    fn ical_import() {
+        let text = "BEGIN:VCALENDAR\r\nBEGIN:VTIMEZONE\r\nTZID:Europe/Berlin\r\n\
+            BEGIN:DAYLIGHT\r\nTZOFFSETTO:+0200\r\nDTSTART:19810329T020000\r\nRRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\r\nEND:DAYLIGHT\r\n\
+            BEGIN:STANDARD\r\nTZOFFSETTO:+0100\r\nDTSTART:19961027T030000\r\nRRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r\nEND:STANDARD\r\n\
+            END:VTIMEZONE\r\n\
+            BEGIN:VTODO\r\nSUMMARY:folded\r\n  summary\r\n\tand tab\r\nDESCRIPTION:notes\r\nDTSTART;TZID=Europe/Berlin:20240701T120000\r\n\
+            BEGIN:VALARM\r\nDESCRIPTION:alarm\r\nEND:VALARM\r\nDUE;VALUE=DATE:20240710\r\nEND:VTODO\r\n\
+            BEGIN:VTODO\r\nSUMMARY:winter\r\nDTSTART;TZID=Europe/Berlin:20240115T120000\r\nEND:VTODO\r\n\
+            BEGIN:VTODO\r\nSUMMARY:utc\r\nDTSTART:20240115T120000Z\r\nEND:VTODO\r\n\
+            END:VCALENDAR\r\n";
+        let items = import_ical(text).unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].task, "folded summaryand tab");
+        assert_eq!(items[0].notes, "notes");
+        assert_eq!(items[0].start, day(2024, 7, 1) + 10 * 3_600);
+        assert_eq!(items[0].due, Some(day(2024, 7, 10)));
+        assert!(items[0].uid.is_empty());
+        assert_eq!(items[1].start, day(2024, 1, 15) + 11 * 3_600);
+        assert_eq!(items[2].start, day(2024, 1, 15) + 12 * 3_600);
+        assert!(import_ical("BEGIN:VTODO\r\nSUMMARY:open\r\n").is_err());
+        assert!(import_ical("BEGIN:VTODO\r\nDTSTART:2024\r\nEND:VTODO\r\n").is_err());
+    }
+
+    #[test]
+    fn csv_quoting() {
+        let rows = vec![vec![String::from("a,b"), String::from("say \"hi\""), String::from("plain")]];
+        assert_eq!(export_csv(&["x", "y", "z"], &rows), "x,y,z\r\n\"a,b\",\"say \"\"hi\"\"\",plain\r\n");
+    }
+
+}
@@ -0,0 +1,138 @@
+//! The operation journal for ```undo``` and ```redo```.
+//!
+//! Every change of the database is recorded with the command and the tasks before and after
+//! the change, as they were read from the database file. A backend like todo.txt doesn't store
+//! every detail, so ```undo``` can compare the recorded state with the file. The journal is a
+//! side file of the database, e.g. ```todo.data.journal```, every entry is one line:
+//!
+//! ```undo;TIME;COMMAND;BEFORE;AFTER```
+//!
+//! ```BEFORE``` and ```AFTER``` are the tasks in the format of the CSV file, escaped like a field.
+
+use std::fs;
+use std::path::Path;
+
+use crate::todo_lib::TodoItem;
+use crate::todo_storage::{escape, items_to_text, parse_text, split_escaped, write_atomic};
+
+// the number of changes, which can be undone
+const JOURNAL_KEEP: usize = 50;
+
+/// A recorded change of the database.
+pub struct JournalEntry {
+    pub time: u64,
+    pub command: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// The two stacks of changes, the last entry is the newest.
+pub struct Journal {
+    pub undo: Vec<JournalEntry>,
+    pub redo: Vec<JournalEntry>,
+}
+
+impl Journal {
+
+    /// Load the journal, a missing file is an empty journal and damaged lines are skipped.
+    pub fn load(path: &Path) -> Journal {
+        let mut journal = Journal { undo: Vec::new(), redo: Vec::new() };
+        let content = fs::read_to_string(path).unwrap_or_default();
+        for line in content.lines() {
+            let f = split_escaped(line, ';');
+            if f.len() != 5 {
+                continue;
+            }
+            let time = f[1].parse::<u64>().unwrap_or_default();
+            let entry = JournalEntry { time, command: f[2].clone(), before: f[3].clone(), after: f[4].clone() };
+            match f[0].as_str() {
+                "undo" => journal.undo.push(entry),
+                "redo" => journal.redo.push(entry),
+                _ => {}
+            }
+        }
+        journal
+    }
+
+    /// Save the journal, only the newest entries are kept.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut s = String::new();
+        let first = self.undo.len().saturating_sub(JOURNAL_KEEP);
+        for (stack, entries) in [("undo", &self.undo[first..]), ("redo", &self.redo[..])] {
+            for e in entries {
+                s.push_str(format!("{};{};{};{};{}\n", stack, e.time, escape(&e.command), escape(&e.before), escape(&e.after)).as_str());
+            }
+        }
+        write_atomic(path, &s)
+    }
+
+    /// Record a new change, a new change can't be redone after an undo.
+    pub fn record(&mut self, time: u64, command: &str, before: &[TodoItem], after: &[TodoItem]) {
+        self.undo.push(JournalEntry {
+            time,
+            command: command.to_string(),
+            before: items_to_text(before),
+            after: items_to_text(after),
+        });
+        self.redo.clear();
+    }
+
+}
+
+/// Read the tasks of a recorded state.
+pub fn state_items(state: &str) -> Vec<TodoItem> {
+    match parse_text(state) {
+        Ok(records) => records.into_iter().filter_map(|r| r.item.ok()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Compare the tasks with a recorded state.
+pub fn is_state(items: &[TodoItem], state: &str) -> bool {
+    items_to_text(items) == state
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn save_and_load() {
+        let path = std::env::temp_dir().join(format!("todo-test-{}.journal", std::process::id()));
+        let mut ds = TodoItem::from(1, String::from("task; with\nline break"), 1_714_564_800, false);
+        let before = vec![ds.clone()];
+        ds.is_done = true;
+        let after = vec![ds];
+        let mut journal = Journal { undo: Vec::new(), redo: Vec::new() };
+        journal.record(1_714_600_000, "done 1", &before, &after);
+        journal.save(&path).unwrap();
+        let journal = Journal::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(journal.undo.len(), 1);
+        assert!(journal.redo.is_empty());
+        let entry = &journal.undo[0];
+        assert_eq!((entry.time, entry.command.as_str()), (1_714_600_000, "done 1"));
+        assert!(is_state(&after, &entry.after));
+        assert!(!is_state(&before, &entry.after));
+        let items = state_items(&entry.before);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].task, "task; with\nline break");
+        assert!(!items[0].is_done);
+    }
+
+    #[test]
+    fn keep_newest() {
+        let mut journal = Journal { undo: Vec::new(), redo: Vec::new() };
+        for i in 0..JOURNAL_KEEP + 5 {
+            journal.record(i as u64, format!("add {}", i).as_str(), &[], &[]);
+        }
+        let path = std::env::temp_dir().join(format!("todo-test-keep-{}.journal", std::process::id()));
+        journal.save(&path).unwrap();
+        let journal = Journal::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(journal.undo.len(), JOURNAL_KEEP);
+        assert_eq!(journal.undo.last().map(|e| e.time), Some(JOURNAL_KEEP as u64 + 4));
+    }
+
+}
@@ -0,0 +1,370 @@
+//! A very small JSON reader and writer, enough for the ToDo database and the export.
+//!
+//! No external crate is used, the parser follows RFC 8259 and the writer produces
+//! indented JSON, which is easy to read and to ```diff```.
+
+use std::fmt::Write;
+
+/// A JSON value, the objects keep the order of their keys.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+
+    /// Get the value of ```key``` if this is an object.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(o) => o.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// The value as ```u64```, only whole and positive numbers are accepted.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            JsonValue::Number(n) if *n >= 0.0 && n.fract() == 0.0 && *n <= u64::MAX as f64 => Some(*n as u64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Write the value as compact JSON on one line.
+    pub fn to_compact(&self) -> String {
+        let mut s = String::new();
+        write_value(&mut s, self, None, 0);
+        s
+    }
+
+    /// Write the value as indented JSON.
+    pub fn to_pretty(&self) -> String {
+        let mut s = String::new();
+        write_value(&mut s, self, Some(2), 0);
+        s.push('\n');
+        s
+    }
+
+}
+
+/// For internal use.
+///
+/// Write a value, ```indent``` is ```None``` for compact JSON.
+fn write_value(s: &mut String, value: &JsonValue, indent: Option<usize>, level: usize) {
+    match value {
+        JsonValue::Null => s.push_str("null"),
+        JsonValue::Bool(b) => s.push_str(if *b { "true" } else { "false" }),
+        JsonValue::Number(n) => {
+            if n.is_finite() {
+                let _ = write!(s, "{}", n);
+            } else {
+                s.push_str("null");
+            }
+        }
+        JsonValue::String(st) => write_string(s, st),
+        JsonValue::Array(a) => {
+            if a.is_empty() {
+                s.push_str("[]");
+                return;
+            }
+            s.push('[');
+            for (i, v) in a.iter().enumerate() {
+                if i > 0 {
+                    s.push(',');
+                }
+                new_line(s, indent, level + 1);
+                write_value(s, v, indent, level + 1);
+            }
+            new_line(s, indent, level);
+            s.push(']');
+        }
+        JsonValue::Object(o) => {
+            if o.is_empty() {
+                s.push_str("{}");
+                return;
+            }
+            s.push('{');
+            for (i, (k, v)) in o.iter().enumerate() {
+                if i > 0 {
+                    s.push(',');
+                }
+                new_line(s, indent, level + 1);
+                write_string(s, k);
+                s.push(':');
+                if indent.is_some() {
+                    s.push(' ');
+                }
+                write_value(s, v, indent, level + 1);
+            }
+            new_line(s, indent, level);
+            s.push('}');
+        }
+    }
+}
+
+/// For internal use.
+fn new_line(s: &mut String, indent: Option<usize>, level: usize) {
+    if let Some(i) = indent {
+        s.push('\n');
+        s.push_str(" ".repeat(i * level).as_str());
+    }
+}
+
+/// For internal use.
+///
+/// Write a string with all necessary escapes.
+fn write_string(s: &mut String, st: &str) {
+    s.push('"');
+    for c in st.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            '\t' => s.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(s, "\\u{:04x}", c as u32);
+            }
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+}
+
+/// Parse a JSON text, on errors the message contains the position.
+pub fn parse(text: &str) -> Result<JsonValue, String> {
+    let mut p = Parser { chars: text.chars().collect(), pos: 0 };
+    let value = p.value()?;
+    p.skip_ws();
+    if p.pos < p.chars.len() {
+        return Err(p.error("unexpected characters after the end"));
+    }
+    Ok(value)
+}
+
+/// For internal use, a simple recursive descent parser.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+
+    fn error(&self, msg: &str) -> String {
+        format!("{} at character {}", msg, self.pos + 1)
+    }
+
+    fn skip_ws(&mut self) {
+        while self.pos < self.chars.len() && matches!(self.chars[self.pos], ' ' | '\t' | '\n' | '\r') {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, word: &str) -> Result<(), String> {
+        for c in word.chars() {
+            if self.peek() != Some(c) {
+                return Err(self.error(format!("expected '{}'", word).as_str()));
+            }
+            self.pos += 1;
+        }
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<JsonValue, String> {
+        self.skip_ws();
+        match self.peek() {
+            Some('n') => self.expect("null").map(|_| JsonValue::Null),
+            Some('t') => self.expect("true").map(|_| JsonValue::Bool(true)),
+            Some('f') => self.expect("false").map(|_| JsonValue::Bool(false)),
+            Some('"') => self.string().map(JsonValue::String),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue, String> {
+        let mut a: Vec<JsonValue> = Vec::new();
+        self.pos += 1;
+        self.skip_ws();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(JsonValue::Array(a));
+        }
+        loop {
+            a.push(self.value()?);
+            self.skip_ws();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Array(a));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, String> {
+        let mut o: Vec<(String, JsonValue)> = Vec::new();
+        self.pos += 1;
+        self.skip_ws();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(JsonValue::Object(o));
+        }
+        loop {
+            self.skip_ws();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a key"));
+            }
+            let key = self.string()?;
+            self.skip_ws();
+            self.expect(":")?;
+            let value = self.value()?;
+            o.push((key, value));
+            self.skip_ws();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Object(o));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut n: u32 = 0;
+        for _ in 0..4 {
+            let d = self.peek().and_then(|c| c.to_digit(16)).ok_or_else(|| self.error("invalid unicode escape"))?;
+            n = n * 16 + d;
+            self.pos += 1;
+        }
+        Ok(n)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let mut s = String::new();
+        self.pos += 1;
+        loop {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.pos += 1;
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let e = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += 1;
+                    match e {
+                        '"' => s.push('"'),
+                        '\\' => s.push('\\'),
+                        '/' => s.push('/'),
+                        'b' => s.push('\u{8}'),
+                        'f' => s.push('\u{c}'),
+                        'n' => s.push('\n'),
+                        'r' => s.push('\r'),
+                        't' => s.push('\t'),
+                        'u' => {
+                            let mut n = self.hex4()?;
+                            // a surrogate pair is needed for characters outside the BMP
+                            if (0xD800..0xDC00).contains(&n) && self.peek() == Some('\\') {
+                                self.pos += 1;
+                                self.expect("u")?;
+                                let low = self.hex4()?;
+                                n = 0x10000 + ((n - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+                            }
+                            s.push(char::from_u32(n).unwrap_or('\u{FFFD}'));
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<JsonValue, String> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E') {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        match text.parse::<f64>() {
+            Ok(n) => Ok(JsonValue::Number(n)),
+            Err(_) => Err(self.error(format!("invalid number '{}'", text).as_str())),
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let value = JsonValue::Object(vec![
+            (String::from("text"), JsonValue::String(String::from("quote \" backslash \\ line\n tab\t ü ☕ \u{1}"))),
+            (String::from("number"), JsonValue::Number(1_714_564_800.0)),
+            (String::from("list"), JsonValue::Array(vec![JsonValue::Bool(true), JsonValue::Null, JsonValue::Number(-1.5)])),
+            (String::from("empty"), JsonValue::Object(Vec::new())),
+        ]);
+        assert_eq!(parse(&value.to_pretty()).unwrap(), value);
+        assert_eq!(parse(&value.to_compact()).unwrap(), value);
+        assert!(!value.to_compact().contains('\n'));
+    }
+
+    #[test]
+    fn parse_values() {
+        assert_eq!(parse(r#""ü😀\/""#).unwrap(), JsonValue::String(String::from("ü😀/")));
+        assert_eq!(parse(" [1e3, 0.5, -0] ").unwrap().as_array().map(|a| a.len()), Some(3));
+        let json = parse(r#"{"a": 12, "b": "x"}"#).unwrap();
+        assert_eq!(json.get("a").and_then(|a| a.as_u64()), Some(12));
+        assert_eq!(json.get("b").and_then(|b| b.as_str()), Some("x"));
+        assert_eq!(parse("1.5").unwrap().as_u64(), None);
+        for invalid in ["", "{", "[1,]", "{\"a\" 1}", "tru", "\"open", "1 2"] {
+            assert!(parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+}
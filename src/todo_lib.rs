@@ -1,34 +1,118 @@
 use std::env;
-use std::fs::{copy, create_dir, File};
+use std::fs::{self, copy, create_dir_all, read_dir, File, OpenOptions, TryLockError};
+use std::hash::{BuildHasher, DefaultHasher, Hash, Hasher, RandomState};
 use std::io;
 // the imports make me wonder sometimes why these aren't available with use std::io::*;
-use std::io::{BufReader, BufWriter, IsTerminal};
+use std::io::IsTerminal;
 use std::io::prelude::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::OnceLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::todo_export::{export_csv, export_ical, export_json, export_markdown, export_todotxt};
+use crate::todo_export::{import_ical, import_json, import_markdown, import_todotxt};
+use crate::todo_config::{config, APP_CONFIG, CMD_CONFIG};
+use crate::todo_date::{anchor_recur, civil_from_secs, date_format, date_string, days_from_civil, file_stamp, format_time, next_due, parse_date, parse_due, parse_recur, weekday};
+use crate::todo_journal::{is_state, state_items, Journal};
+use crate::todo_migrate::{check_version, link_by_uid, migrations_from};
+use crate::todo_storage::{open_storage, storage_file_name, Storage, FORMAT_VERSION};
+
 pub const CMD_ADD: &str = "add";
+pub const CMD_BACKUPS: &str = "backups";
+pub const CMD_BLOCK: &str = "block";
+pub const CMD_CHECK: &str = "check";
 pub const CMD_DONE: &str = "done";
+pub const CMD_DUE: &str = "due";
 pub const CMD_EDIT: &str = "edit";
+pub const CMD_EXPORT: &str = "export";
 pub const CMD_HELP: &str = "help";
+pub const CMD_IMPORT: &str = "import";
+pub const CMD_INIT: &str = "init";
 pub const CMD_LIST: &str = "list";
+pub const CMD_LISTS: &str = "lists";
+pub const CMD_MOVE: &str = "move";
+pub const CMD_NOTE: &str = "note";
+pub const CMD_PRIO: &str = "prio";
+pub const CMD_READY: &str = "ready";
+pub const CMD_RECUR: &str = "recur";
 pub const CMD_REMOVE: &str = "remove";
 pub const CMD_RESET: &str = "reset";
+pub const CMD_REDO: &str = "redo";
 pub const CMD_RESTORE: &str = "restore";
-
+pub const CMD_SHOW: &str = "show";
+pub const CMD_START: &str = "start";
+pub const CMD_STOP: &str = "stop";
+pub const CMD_TAGS: &str = "tags";
+pub const CMD_TIME: &str = "time";
+pub const CMD_UNBLOCK: &str = "unblock";
+pub const CMD_UNDO: &str = "undo";
+pub const CMD_UNDONE: &str = "undone";
+
+// all commands with their other names and the options without a command, an alias can't replace them
+const COMMANDS: &[&str] = &[CMD_ADD, CMD_BACKUPS, CMD_BLOCK, CMD_CHECK, "fsck", CMD_CONFIG, CMD_DONE, CMD_DUE, CMD_EDIT,
+    CMD_EXPORT, CMD_HELP, CMD_IMPORT, CMD_INIT, CMD_LIST, CMD_LISTS, CMD_MOVE, CMD_NOTE, "notes", CMD_PRIO, "priority",
+    CMD_READY, CMD_RECUR, CMD_REDO, CMD_REMOVE, CMD_RESET, CMD_RESTORE, CMD_SHOW, CMD_START, CMD_STOP, CMD_TAGS,
+    CMD_TIME, CMD_UNBLOCK, CMD_UNDO, CMD_UNDONE, "-h", "--help", "-v", "--version"];
+
+// the directory in the user dir before the XDG directories were used, it is moved once
 const APP_SUBDIR: &str = ".r_todo";
-// const APP_CONFIG: &str = "todo.conf";
-const APP_DATABASE: &str = "todo.data";
-const APP_BACKUP: &str = "todo.data.bak";
+const APP_NAME: &str = "todo";
+
+// the side files are named after the database file, e.g. todo.data.lock
+const EXT_BACKUP: &str = "bak";
+const EXT_REJECTED: &str = "rejected";
+const EXT_LOCK: &str = "lock";
+const EXT_JOURNAL: &str = "journal";
+
+// the task list of a project, it's found in the working directory or a parent directory
+const PROJECT_FILE: &str = ".todo";
+
+// the named lists are stored in a subdirectory of the data directory, e.g. lists/work.data
+const APP_LISTS: &str = "lists";
+const DEFAULT_LIST: &str = "default";
+
+// the snapshots before every change, e.g. backups/todo-20240501-120000.data
+const APP_BACKUPS: &str = "backups";
+
+/// For internal use.
+///
+/// The options of the command ```list```.
+struct ListOptions {
+    filter: String,
+    sort: String,
+    priority: Option<char>,
+    // the tags, which a task must have, and the tags, which it must not have
+    tags: Vec<String>,
+    without: Vec<String>,
+    // show the short permanent ID after the number
+    uids: bool,
+}
 
 /// Structure for internal use, every task is stored in this simple structure.
 #[derive(Clone, Debug)]
-struct TodoItem {
-    index: u32,
-    task: String,
-    start: u64,
-    is_done: bool,
+pub(crate) struct TodoItem {
+    pub(crate) index: u32,
+    // the permanent ID of the task, a UUID, the index is only the number in the list
+    pub(crate) uid: String,
+    pub(crate) task: String,
+    pub(crate) start: u64,
+    pub(crate) is_done: bool,
+    pub(crate) priority: Option<char>,
+    pub(crate) due: Option<u64>,
+    pub(crate) tags: Vec<String>,
+    // the time of completion, it's unknown for the tasks done before it was recorded
+    pub(crate) completed: Option<u64>,
+    // the permanent ID of the parent task, if the task is a subtask
+    pub(crate) parent: Option<String>,
+    // the permanent IDs of the tasks, which must be done before this task can start
+    pub(crate) depends: Vec<String>,
+    // the rule of a recurring task, e.g. 1w, friday or +3d
+    pub(crate) recur: Option<String>,
+    // the notes of the task, they can have more lines
+    pub(crate) notes: String,
+    // the tracked work sessions as start and end time, the running session has no end
+    pub(crate) sessions: Vec<(u64, Option<u64>)>,
 }
 
 impl TodoItem {
@@ -37,19 +121,39 @@ impl TodoItem {
     fn new() -> TodoItem {
         TodoItem {
             index: 0,
+            uid: new_uid(),
             task: String::default(),
             start: 0,
             is_done: false,
+            priority: None,
+            due: None,
+            tags: Vec::new(),
+            completed: None,
+            parent: None,
+            depends: Vec::new(),
+            recur: None,
+            notes: String::new(),
+            sessions: Vec::new(),
         }
     }
 
     /// Create a new ```TodoItem``` from the parameters
-    fn from(ix: u32, tsk: String, time: u64, done: bool) -> TodoItem {
+    pub(crate) fn from(ix: u32, tsk: String, time: u64, done: bool) -> TodoItem {
         TodoItem {
             index: ix,
+            uid: new_uid(),
             task: tsk,
             start: time,
             is_done: done,
+            priority: None,
+            due: None,
+            tags: Vec::new(),
+            completed: None,
+            parent: None,
+            depends: Vec::new(),
+            recur: None,
+            notes: String::new(),
+            sessions: Vec::new(),
         }
     }
 
@@ -64,6 +168,14 @@ impl TodoItem {
 
 }
 
+/// Structure for internal use, a line of the database file that couldn't be read.
+#[derive(Clone, Debug)]
+struct DamagedLine {
+    nr: usize,
+    line: String,
+    reason: String,
+}
+
 /// The container structure for the task database.
 ///
 /// From the ```main()``` function the arguments where collected in a
@@ -75,103 +187,303 @@ impl TodoItem {
 /// that Vector is much faster than e.g. LinkedLists.*
 pub struct TodoDatabase {
     v: Vec<TodoItem>,
+    storage: Box<dyn Storage>,
+    damaged: Vec<DamagedLine>,
+    bad_index: Vec<DamagedLine>,
+    changed: bool,
+    loaded_hash: Option<u64>,
+    loaded: Vec<TodoItem>,
+    journal: Option<Journal>,
+    version: u32,
 }
 
 impl TodoDatabase {
 
     /// Create a new ```TodoDatabase```
-    fn new() -> TodoDatabase {
+    fn new(storage: Box<dyn Storage>) -> TodoDatabase {
         TodoDatabase {
             v: Vec::new(),
+            storage,
+            damaged: Vec::new(),
+            bad_index: Vec::new(),
+            changed: false,
+            loaded_hash: None,
+            loaded: Vec::new(),
+            journal: None,
+            version: FORMAT_VERSION,
         }
     }
 
-    /// Load the CSV file and initialize the Structure with their childs.
+    /// Load the database and initialize the Structure with their childs.
     ///
     /// The path is OS depended. The HOME directory environment variables
-    /// will be used with the subdir ```.r_todo```to save the CSV file.
+    /// will be used with the subdir ```.r_todo```to save the database.
     ///
     /// For example the Linux path: ```/home/USERNAME/.r_todo/todo.data```
+    ///
+    /// The format of the file depends on the storage backend, see ```todo_storage```.
     pub fn load() -> TodoDatabase {
+        let mut todo_db = TodoDatabase::load_from(get_storage());
+        todo_db.migrate();
+        todo_db
+    }
 
-        let mut todo_db = TodoDatabase::new();
-        let app_dir = get_os_data_dir();
+    /// For internal use.
+    ///
+    /// Load the database from the storage backend, e.g. another list.
+    fn load_from(storage: Box<dyn Storage>) -> TodoDatabase {
 
-        let file_name = Path::new(&app_dir).join(APP_SUBDIR).join(APP_DATABASE);
+        let mut todo_db = TodoDatabase::new(storage);
+
+        let file_name = todo_db.storage.path().to_path_buf();
         if file_name.exists() {
 
-            let file = match File::open(&file_name) {
-                Ok(file) => file,
+            let content = match fs::read(&file_name) {
+                Ok(content) => content,
                 Err(err) => {
                     eprintln!("Couldn't open {} (error: {}).", file_name.display(), err);
                     process::exit(1)
                 }
             };
 
-            let reader = BufReader::new(file);
-            for line in reader.lines() {
+            // remember the content, so save() can detect changes from another process
+            todo_db.loaded_hash = Some(hash_bytes(&content));
 
-                let l = line.unwrap_or_default();
+            // a file of a newer version would lose the new fields
+            let version = todo_db.storage.version().and_then(|v| check_version(v).map(|_| v));
+            todo_db.version = match version {
+                Ok(version) => version,
+                Err(err) => {
+                    eprintln!("Couldn't load {} (error: {}).", file_name.display(), err);
+                    process::exit(1)
+                }
+            };
 
-                if l.starts_with(";") || l.starts_with("#") || l.starts_with(" ") || l.is_empty() {
-                    continue;
+            let records = match todo_db.storage.load() {
+                Ok(records) => records,
+                Err(err) => {
+                    eprintln!("Couldn't load {} (error: {}).", file_name.display(), err);
+                    process::exit(1)
                 }
+            };
 
-                // that's nice and fast
-                let l_v: Vec<&str> = l.split(';').collect();
-                if l_v.len() == 4 {
-                    let ds = TodoItem::from(
-                        l_v[0].parse::<u32>().unwrap(),
-                        l_v[1].to_string(),
-                        l_v[2].parse::<u64>().unwrap(),
-                        l_v[3].parse::<bool>().unwrap(),
-                    );
-                    todo_db.v.push(ds);
+            for r in records {
+
+                match r.item {
+                    Ok(ds) => {
+                        if ds.index == 0 {
+                            todo_db.bad_index.push(DamagedLine { nr: r.nr, line: r.raw, reason: String::from("the index is 0") });
+                        } else if todo_db.v.iter().any(|i| i.index == ds.index) {
+                            let reason = format!("duplicate index {}", ds.index);
+                            todo_db.bad_index.push(DamagedLine { nr: r.nr, line: r.raw, reason });
+                        } else if todo_db.v.iter().any(|i| i.uid == ds.uid) {
+                            let reason = format!("duplicate id {}", ds.uid);
+                            todo_db.bad_index.push(DamagedLine { nr: r.nr, line: r.raw, reason });
+                        }
+                        todo_db.v.push(ds);
+                    }
+                    Err(reason) => todo_db.damaged.push(DamagedLine { nr: r.nr, line: r.raw, reason }),
                 }
 
             }
 
+            let problems = todo_db.damaged.len() + todo_db.bad_index.len();
+            if problems > 0 {
+                eprintln!("Found {} problem(s) in {}, use the command '{}' to repair it.", problems, file_name.display(), CMD_CHECK);
+            }
+
+            // the state before the command, it's recorded in the journal
+            todo_db.loaded = todo_db.v.clone();
+
         } else {
-            println!("ToDo database does not exist, creating a new one.");
+            // stderr, so the message doesn't end up in an export
+            eprintln!("ToDo database does not exist, creating a new one.");
         }
 
         todo_db
 
     }
 
-    /// Save the database as a simple CSV file.
-    pub fn save(&self) {
+    /// For internal use.
+    ///
+    /// Save a database of an older format in the current format, the old file is kept as
+    /// e.g. ```todo.data.v1.bak```. A damaged database is migrated after its repair.
+    fn migrate(&mut self) {
 
-        let app_dir = get_os_data_dir();
-        let app_subdir = Path::new(&app_dir).join(APP_SUBDIR);
-        if !app_subdir.exists() {
-            let _dir = match create_dir(&app_subdir) {
-                Err(err) => {
-                    eprintln!("Can't create directory: {} (error: {})", app_subdir.display(), err);
-                    process::exit(1)
-                }
-                Ok(dir) => dir,
-            };
+        if self.version >= FORMAT_VERSION || !self.damaged.is_empty() {
+            return;
         }
-        let file_name = app_subdir.join(APP_DATABASE);
 
-        let file = match File::create(&file_name) {
-            Ok(file)    => file,
-            Err(err)    => {
-                            eprintln!("Can't create database file: {} (error: {})", file_name.display(), err);
-                            process::exit(1)
+        let file_name = self.storage.path().to_path_buf();
+        let backup = side_file(&file_name, format!("v{}.{}", self.version, EXT_BACKUP).as_str());
+        if let Err(err) = copy(&file_name, &backup) {
+            eprintln!("Can't copy database: {} (error: {})", backup.display(), err);
+            process::exit(1)
+        }
+        if let Err(err) = self.storage.save(&self.v) {
+            eprintln!("Can't write database: {} (error: {})", file_name.display(), err);
+            process::exit(1)
+        }
+        self.loaded_hash = fs::read(&file_name).ok().map(|content| hash_bytes(&content));
+
+        eprintln!("The database {} was converted from format {} to {}, the old file is kept as {}.",
+            file_name.display(), self.version, FORMAT_VERSION, backup.display());
+        for m in migrations_from(self.version) {
+            eprintln!("- format {}: {}", m.version, m.description);
+        }
+        self.version = FORMAT_VERSION;
+
+    }
+
+    /// Save the database with the storage backend.
+    ///
+    /// A damaged database is never saved, the unreadable lines would be lost. If the file
+    /// was changed on disk since it was loaded, it is not overwritten. Before the database is
+    /// saved, a snapshot of the old database is stored in the ```backups``` directory and the
+    /// ```command``` is recorded in the journal for ```undo```.
+    pub fn save(&self, command: &str) {
+
+        if !self.damaged.is_empty() {
+            eprintln!("The database is damaged and was not saved, use the command '{}' to repair it.", CMD_CHECK);
+            process::exit(1)
+        }
+
+        let file_name = self.storage.path();
+
+        let on_disk = fs::read(file_name).ok().map(|content| hash_bytes(&content));
+        if on_disk != self.loaded_hash {
+            eprintln!("The database {} was changed on disk since it was loaded, nothing was saved.", file_name.display());
+            process::exit(1)
+        }
+
+        if on_disk.is_some() {
+            self.snapshot();
+        }
+
+        if let Err(err) = self.storage.save(&self.v) {
+            eprintln!("Can't write database file: {} (error: {})", file_name.display(), err);
+            process::exit(1)
+        }
+
+        // undo and redo have already changed the journal
+        let journal_name = side_file(file_name, EXT_JOURNAL);
+        let result = match &self.journal {
+            Some(journal) => journal.save(&journal_name),
+            None => {
+                // the state as it was saved, a backend like todo.txt doesn't store every detail
+                let saved = match self.storage.load() {
+                    Ok(records) => records.into_iter().filter_map(|r| r.item.ok()).collect(),
+                    Err(_) => self.v.clone(),
+                };
+                let mut journal = Journal::load(&journal_name);
+                journal.record(time_now(), command, &self.loaded, &saved);
+                journal.save(&journal_name)
             }
         };
+        if let Err(err) = result {
+            eprintln!("Can't write journal: {} (error: {})", journal_name.display(), err);
+        }
 
-        let mut writer = BufWriter::new(file);
-        writer.write_fmt(format_args!("# ToDo list database\n\n")).unwrap();
-        for ds in &self.v {
-            writer.write_fmt(format_args!("{};{};{};{}\n", ds.index, ds.task, ds.start, ds.is_done)).unwrap();
+    }
+
+    /// For internal use.
+    ///
+    /// Copy the database file into the ```backups``` directory and remove the oldest snapshots,
+    /// the number of kept snapshots is the key ```backup.keep``` of the configuration.
+    fn snapshot(&self) {
+
+        let keep = config().backup_keep();
+        if keep == 0 {
+            return;
+        }
+
+        let dir = self.backup_dir();
+        if let Err(err) = create_dir_all(&dir) {
+            eprintln!("Can't create directory: {} (error: {})", dir.display(), err);
+            process::exit(1)
+        }
+
+        let (stem, ext) = self.backup_name();
+        let stamp = file_stamp(time_now());
+        // the number follows the newest snapshot of the same second, a removed old one isn't reused
+        let nr = self.list_backups().iter().filter_map(|p| backup_order(p, &stem)).filter(|(s, _)| *s == stamp).map(|(_, nr)| nr).max();
+        let snapshot = match nr {
+            Some(nr) => dir.join(format!("{}-{}-{}.{}", stem, stamp, nr + 1, ext)),
+            None => dir.join(format!("{}-{}.{}", stem, stamp, ext)),
+        };
+
+        if let Err(err) = copy(self.storage.path(), &snapshot) {
+            eprintln!("Can't copy database: {} (error: {})", snapshot.display(), err);
+            process::exit(1)
+        }
+
+        let backups = self.list_backups();
+        if backups.len() > keep {
+            for old in &backups[..backups.len() - keep] {
+                let _ = fs::remove_file(old);
+            }
         }
 
-        // save the file operation to disk
-        writer.flush().unwrap();
+    }
+
+    /// For internal use.
+    ///
+    /// The directory of the snapshots, next to the database file.
+    fn backup_dir(&self) -> PathBuf {
+        let path = self.storage.path();
+        if path.ends_with(PROJECT_FILE) {
+            // a project has no backups directory in its root, only the side files of .todo
+            return side_file(path, APP_BACKUPS);
+        }
+        path.parent().unwrap_or(Path::new(".")).join(APP_BACKUPS)
+    }
+
+    /// For internal use.
+    ///
+    /// The stem and extension of the database file, the snapshots are named after them.
+    fn backup_name(&self) -> (String, String) {
+        let path = self.storage.path();
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy().trim_start_matches('.').to_string();
+        let ext = path.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or(String::from("data"));
+        (stem, ext)
+    }
+
+    /// For internal use.
+    ///
+    /// All snapshots of the database, the oldest first.
+    fn list_backups(&self) -> Vec<PathBuf> {
+        let (stem, ext) = self.backup_name();
+        let prefix = format!("{}-", stem);
+        let suffix = format!(".{}", ext);
+        let mut backups: Vec<PathBuf> = match read_dir(self.backup_dir()) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| {
+                let name = p.file_name().unwrap_or_default().to_string_lossy().to_string();
+                // the list work-1 must not show the snapshots of the list work
+                let stamp = name.strip_prefix(&prefix).and_then(|n| n.strip_suffix(&suffix)).unwrap_or_default();
+                stamp.len() >= 15 && stamp.char_indices().take(15).all(|(i, c)| if i == 8 { c == '-' } else { c.is_ascii_digit() })
+            }).collect(),
+            Err(_) => Vec::new(),
+        };
+        backups.sort_by_key(|p| backup_order(p, &stem));
+        backups
+    }
+
+    /// For internal use.
+    ///
+    /// Read the tasks of a snapshot with the storage backend, damaged tasks are skipped.
+    fn load_backup(&self, path: &Path) -> Vec<TodoItem> {
+        match self.storage.with_path(path).load() {
+            Ok(records) => records.into_iter().filter_map(|r| r.item.ok()).collect(),
+            Err(err) => {
+                eprintln!("Couldn't load {} (error: {}).", path.display(), err);
+                process::exit(1)
+            }
+        }
+    }
 
+    /// Returns ```true``` if a command has modified the database and it needs to be saved.
+    pub fn is_changed(&self) -> bool {
+        self.changed
     }
 
     /// Add a new task to the database.
@@ -183,6 +495,10 @@ impl TodoDatabase {
     /// or with quotations:
     ///
     /// ```todo add "Add some documentation to the todo source code."```
+    ///
+    /// The words ```+project``` and ```@context``` in the task are the tags of the task, a tag
+    /// can be given with ```--tag``` too. ```--parent 4``` adds the task as subtask of the task 4,
+    /// ```--every 1w``` adds a recurring task.
     pub fn add(&mut self, argv: &[String]) {
 
         let mut ds =  TodoItem::new();
@@ -191,26 +507,63 @@ impl TodoDatabase {
         let ix = self.get_highest_id() + 1;
         ds.index = ix;
 
+        // the options are only read in front of the task, the task itself can contain everything
+        let mut argv = argv;
+        let mut tags = Vec::new();
+        while argv.len() > 1 && matches!(argv[0].as_str(), "-p" | "--priority" | "-d" | "--due" | "-t" | "--tag" | "--parent" | "-e" | "--every") {
+            match argv[0].as_str() {
+                "-p" | "--priority" => {
+                    ds.priority = parse_priority_or_exit(&argv[1]);
+                    argv = &argv[2..];
+                }
+                "-t" | "--tag" => {
+                    tags.push(tag_or_exit(&argv[1]));
+                    argv = &argv[2..];
+                }
+                "-e" | "--every" => {
+                    ds.recur = Some(parse_recur_or_exit(&argv[1]));
+                    argv = &argv[2..];
+                }
+                "--parent" => {
+                    ds.parent = Some(self.uid_of(self.index_or_exit(&argv[1])));
+                    argv = &argv[2..];
+                }
+                _ => {
+                    let (due, words) = take_due_or_exit(&argv[1..]);
+                    ds.due = Some(due);
+                    argv = &argv[1 + words..];
+                }
+            }
+        }
+
         let mut new_task = String::new();
         for i in argv {
             new_task.push_str(i);
             new_task.push(' ');
         }
-        ds.task = new_task.trim_end().to_string();
+        (ds.task, ds.tags) = split_tags(&new_task);
+        for tag in tags {
+            if !ds.tags.contains(&tag) {
+                ds.tags.push(tag);
+            }
+        }
 
         match SystemTime::now().duration_since(UNIX_EPOCH) {
             Ok(t) => ds.start = t.as_secs(),
             Err(_) => eprintln!("Error: SystemTime::UNIX_EPOCH."), // can this really happen?
         };
+        ds.recur = ds.recur.map(|rule| anchor_recur(&rule, ds.due, ds.start));
 
+        let uid = ds.uid.clone();
         self.v.push(ds);
+        self.changed = true;
 
         // escape sequences for colourful messages must be in Hex format, not Octal like in
         // linux terminals, seems to be an issue in Rust
-        if io::stdout().is_terminal() {
-            println!("Added a task with id \x1B[92m{}\x1B[39m.", ix);
+        if use_color() {
+            println!("Added a task with id \x1B[92m{}\x1B[39m ({}).", ix, self.short_uid(&uid));
         } else {
-            println!("Added a task with id {}.", ix);
+            println!("Added a task with id {} ({}).", ix, self.short_uid(&uid));
         }
 
     }
@@ -220,327 +573,2506 @@ impl TodoDatabase {
     /// **Command:**
     ///
     /// ```todo done 2```
+    ///
+    /// The open subtasks are marked as done too, if the user agrees or with ```--children```. When a
+    /// recurring task is done, the next occurrence is added as new task.
     pub fn done(&mut self, argv: &[String]) {
 
-        let item: u32 = argv[0].parse::<u32>().unwrap_or_default();
-        if item > 0 {
+        let Some(arg) = argv.first() else {
+            eprintln!("Missing argument, try {} INDEX [--children]", CMD_DONE);
+            process::exit(1)
+        };
+
+        let item = self.index_or_exit(arg);
+        let children = argv[1..].iter().any(|a| a == "-c" || a == "--children");
+        let blocked: Vec<u32> = self.v.iter().filter(|ds| !ds.is_done && !self.blockers(ds).is_empty()).map(|ds| ds.index).collect();
+        let mut next: Option<TodoItem> = None;
 
-            for ds in &mut self.v {
+        for ds in &mut self.v {
 
-                if ds.index == item {
+            if ds.index == item {
 
-                    ds.is_done = true;
-                    if io::stdout().is_terminal() {
-                        println!("Task with id \x1B[92m{}\x1B[39m marked as done.", item);
-                    } else {
-                        println!("Task with id {} marked as done.", item);
-                    }
+                if ds.is_done {
+                    println!("Task with id {} is already done.", item);
                     break;
-
                 }
+                ds.is_done = true;
+                ds.completed = Some(time_now());
+                self.changed = true;
+                if use_color() {
+                    println!("Task with id \x1B[92m{}\x1B[39m marked as done.", item);
+                } else {
+                    println!("Task with id {} marked as done.", item);
+                }
+                // the rule moves to the next occurrence, the finished task doesn't recur anymore
+                if let Some(mut rule) = ds.recur.take() {
+                    // a rule of an older version doesn't know the day of the series
+                    if !rule.contains('@') {
+                        rule = anchor_recur(&rule, ds.due, ds.start);
+                    }
+                    let mut ds = ds.clone();
+                    ds.uid = new_uid();
+                    ds.sessions.clear();
+                    ds.due = next_due(&rule, ds.due, ds.start, time_now());
+                    ds.recur = Some(rule);
+                    next = Some(ds);
+                }
+                break;
+
+            }
+
+        }
 
+        let subtasks = self.descendants(item);
+        let open = self.v.iter().filter(|ds| !ds.is_done && subtasks.contains(&ds.index)).count();
+        if open > 0 && (children || confirm(format!("Mark the {} open subtask(s) as done too (y|n)?", open).as_str())) {
+            let now = time_now();
+            for ds in self.v.iter_mut().filter(|ds| !ds.is_done && subtasks.contains(&ds.index)) {
+                ds.is_done = true;
+                ds.completed = Some(now);
             }
+            println!("Marked {} subtask(s) as done.", open);
+        }
+
+        if let Some(mut ds) = next {
+            ds.index = self.get_highest_id() + 1;
+            ds.start = time_now();
+            ds.is_done = false;
+            ds.completed = None;
+            let due = ds.due.map(|d| format!(", it's due on {}", due_date(d))).unwrap_or_default();
+            if use_color() {
+                println!("The next occurrence is the task with id \x1B[92m{}\x1B[39m{}.", ds.index, due);
+            } else {
+                println!("The next occurrence is the task with id {}{}.", ds.index, due);
+            }
+            self.v.push(ds);
+        }
+
+        // a finished task isn't tracked anymore
+        if self.running().is_some_and(|r| self.v.iter().any(|ds| ds.index == r && ds.is_done)) {
+            self.stop_running();
+        }
 
+        let unblocked: Vec<String> = self.v.iter()
+            .filter(|ds| blocked.contains(&ds.index) && self.blockers(ds).is_empty())
+            .map(|ds| format!("{}. {}", ds.index, ds.task))
+            .collect();
+        if !unblocked.is_empty() {
+            println!("Unblocked {} task(s):", unblocked.len());
+            for task in unblocked {
+                println!("  {}", task);
+            }
         }
 
     }
 
-    /// Edit an existing task, you need the ID for the task, get the ID's from ```todo list```
+    /// Let a task depend on other tasks, it's blocked until they are done. A dependency, which
+    /// would create a cycle, is rejected.
     ///
     /// **Command:**
     ///
-    /// ```todo edit "Add an interactive mode to the todo source."```
-    pub fn edit(&mut self, argv: &[String]) {
+    /// ```todo block 7 --by 3``` or ```todo block 7 --by 3,4```
+    pub fn block(&mut self, argv: &[String]) {
 
-        let item: u32 = argv[0].parse::<u32>().unwrap_or_default();
+        let Some(by) = argv.iter().position(|a| a == "--by").filter(|pos| *pos == 1 && argv.len() > 2) else {
+            eprintln!("Missing argument, try {} INDEX --by INDEX", CMD_BLOCK);
+            process::exit(1)
+        };
 
-        if item > 0 {
+        let item = self.index_or_exit(&argv[0]);
+        let blockers: Vec<u32> = argv[by + 1..].iter().flat_map(|a| a.split(',')).filter(|a| !a.is_empty())
+            .map(|a| self.index_or_exit(a))
+            .collect();
 
-            let mut new_task = String::from("");
-            for i in &argv[1..] {
-                new_task.push_str(i);
-                new_task.push(' ');
+        for blocker in blockers {
+            if blocker == item || self.depends_on(blocker, item) {
+                eprintln!("Task {} can't depend on task {}, that would be a cycle.", item, blocker);
+                process::exit(1)
             }
-
-            // here I search for my internal task ID, it's the same as "Vector[i] + 1", but I want to try
-            // another way to get the right dataset
-            for ds in &mut self.v {
-
-                if ds.index == item {
-                    ds.task = new_task;
-                    if io::stdout().is_terminal() {
-                        println!("Task with id \x1B[92m{}\x1B[39m edited.", item);
-                    } else {
-                        println!("Task with id {} edited.", item);
-                    }
-                    break;
-                }
-
+            let uid = self.uid_of(blocker);
+            let ds = self.v.iter_mut().find(|ds| ds.index == item).unwrap();
+            if !ds.depends.contains(&uid) {
+                ds.depends.push(uid);
+                self.changed = true;
+            }
+            if use_color() {
+                println!("Task with id \x1B[92m{}\x1B[39m is blocked by task {}.", item, blocker);
+            } else {
+                println!("Task with id {} is blocked by task {}.", item, blocker);
             }
-
         }
 
     }
 
-    /// Show the task list, with all ID's
+    /// Remove the dependencies of a task, without ```--by``` all of them.
     ///
     /// **Command:**
     ///
-    /// ```todo list```
-    pub fn list(&mut self) {
+    /// ```todo unblock 7``` or ```todo unblock 7 --by 3```
+    pub fn unblock(&mut self, argv: &[String]) {
 
-        if self.v.len() > 0 {
-
-            if io::stdout().is_terminal() {
-                println!("\n\x1B[1mTask List:\x1B[0m\n\x1B[1m----------\x1B[0m\n");
-            } else {
-                println!("\nTask List:\n----------\n");
-            }
+        let Some(arg) = argv.first() else {
+            eprintln!("Missing argument, try {} INDEX [--by INDEX]", CMD_UNBLOCK);
+            process::exit(1)
+        };
 
-            let mut ds_done: u32 = 0;
-            let mut ds_open: u32 = 0;
+        let item = self.index_or_exit(arg);
+        let by: Vec<String> = argv[1..].iter().skip_while(|a| *a != "--by").skip(1).flat_map(|a| a.split(','))
+            .filter(|a| !a.is_empty())
+            .map(|a| self.uid_of(self.index_or_exit(a)))
+            .collect();
+
+        let ds = self.v.iter_mut().find(|ds| ds.index == item).unwrap();
+        let before = ds.depends.len();
+        ds.depends.retain(|i| !by.is_empty() && !by.contains(i));
+        let removed = before - ds.depends.len();
+        self.changed = removed > 0;
+        if use_color() {
+            println!("Removed {} dependency(ies) of the task with id \x1B[92m{}\x1B[39m.", removed, item);
+        } else {
+            println!("Removed {} dependency(ies) of the task with id {}.", removed, item);
+        }
 
-            for ds in &mut self.v {
+    }
 
-                let mut line = String::default();
+    /// Show only the open tasks, which are not blocked by another task.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo ready```
+    pub fn ready(&self) {
+        let opts = ListOptions {
+            filter: String::from("ready"),
+            sort: String::from("id"),
+            priority: None,
+            tags: Vec::new(),
+            without: Vec::new(),
+            uids: false,
+        };
+        self.print_tasks(&list_title(&config().list()), &opts);
+    }
 
-                if io::stdout().is_terminal() {
-                    if ds.is_done {
-                        line.push_str("[\x1B[93mX\x1B[39m] ");
-                        ds_done += 1;
-                    } else {
-                        line.push_str("[ ] ");
-                        ds_open += 1;
-                    }
-                    line.push_str(format!("\x1B[92m{:2}.\x1B[39m ", ds.index).as_str());
-                    if ds.is_done {
-                        line.push_str(format!("\x1B[9m{}\x1B[0m", ds.task).as_str());
-                    } else {
-                        line.push_str(ds.task.as_str());
-                    }
-                } else {
-                    if ds.is_done {
-                        line.push_str("[X] ");
-                        ds_done += 1;
-                    } else {
-                        line.push_str("[ ] ");
-                        ds_open += 1;
-                    }
-                    line.push_str(format!("{:2}. ", ds.index).as_str());
-                    if ds.is_done {
-                        line.push_str(format!("{}", ds.task).as_str());
-                    } else {
-                        line.push_str(ds.task.as_str());
-                    }
-                }
-                if ds.is_done == false {
-                    line.push_str(since_string(ds.start).as_str());
-                }
+    /// Reopen a task, which was marked as done, the time of completion is removed.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo undone 2```
+    pub fn undone(&mut self, argv: &[String]) {
 
-                println!("{}", line);
+        let Some(arg) = argv.first() else {
+            eprintln!("Missing argument, try {} INDEX", CMD_UNDONE);
+            process::exit(1)
+        };
 
-            }
-            if ds_done == 1 {
-                if io::stdout().is_terminal() {
-                    println!("\nFound \x1B[92m{}\x1B[39m open task(s) and \x1B[93m{}\x1B[39m is finished.", ds_open, ds_done);
-                } else {
-                    println!("\nFound {} open task(s) and {} is finished.", ds_open, ds_done);
-                }
-            } else {
-                if io::stdout().is_terminal() {
-                    println!("\nFound \x1B[92m{}\x1B[39m open task(s) and \x1B[93m{}\x1B[39m are finished.", ds_open, ds_done);
-                } else {
-                    println!("\nFound {} open task(s) and {} are finished.", ds_open, ds_done);
-                }
-            }
+        let item = self.index_or_exit(arg);
+        let ds = self.v.iter_mut().find(|ds| ds.index == item).unwrap();
 
+        if !ds.is_done {
+            println!("Task with id {} is still open.", item);
+            return;
+        }
+        ds.is_done = false;
+        ds.completed = None;
+        self.changed = true;
+        if use_color() {
+            println!("Task with id \x1B[92m{}\x1B[39m reopened.", item);
         } else {
-            println!("There are no tasks in the list.");
+            println!("Task with id {} reopened.", item);
         }
 
-        println!("");
-
     }
 
-    /// Remove one task, you need the ID for the task, get the ID's from ```todo list```.
-    /// The user will be prompted for confirmation.
+    /// Set the priority of a task, ```A``` is the highest and ```Z``` the lowest priority.
+    /// The names ```high```, ```medium``` and ```low``` are ```A```, ```B``` and ```C```,
+    /// ```none``` removes the priority.
     ///
     /// **Command:**
     ///
-    /// ```todo remove 2```
-    pub fn remove(&mut self, argv: &[String]) {
+    /// ```todo prio 3 high``` or ```todo prio 3 B```
+    pub fn prio(&mut self, argv: &[String]) {
 
-        let item: u32 = argv[0].parse::<u32>().unwrap_or_default();
-        if item > 0 {
-
-            // this search is like the example of the official documentation
-            let pos = &self.v.iter().position(|i| i.index == item).unwrap();
-            let task = &self.v[pos.to_owned()].task;
-
-            println!("Are you sure to delete this task (y|n)?\n-> {}", task);
-            let mut input = [0];
-            let mut stdin = io::stdin();
-            let mut delete: bool = false;
+        if argv.len() < 2 {
+            eprintln!("Missing argument, try {} INDEX PRIORITY", CMD_PRIO);
+            process::exit(1)
+        }
 
-            if stdin.is_terminal() {
+        let item = self.index_or_exit(&argv[0]);
+        let priority = parse_priority_or_exit(&argv[1]);
+        let ds = self.v.iter_mut().find(|ds| ds.index == item).unwrap();
 
-                let _ = stdin.read(&mut input);
+        ds.priority = priority;
+        self.changed = true;
+        let prio = priority.map(|p| format!("priority {}", p)).unwrap_or(String::from("no priority"));
+        if use_color() {
+            println!("Task with id \x1B[92m{}\x1B[39m has {}.", item, prio);
+        } else {
+            println!("Task with id {} has {}.", item, prio);
+        }
 
-                loop {
+    }
 
-                    match input[0] as char {
-                        'y' | 'Y' => {
-                            delete = true;
-                            break;
-                        }
-                        _ => break,
-                    }
+    /// Set the due date of a task or show the open tasks with a due date, the next deadline first.
+    /// A due date is an ISO date or a phrase like ```tomorrow```, ```next friday```, ```in 3 days```
+    /// or ```eow```, ```none``` removes the due date.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo due 3 next friday```, ```todo due``` or ```todo due --days 7```
+    pub fn due(&mut self, argv: &[String]) {
 
-                }
+        if argv.is_empty() || argv[0].starts_with('-') {
+            self.print_due(argv);
+            return;
+        }
+        if argv.len() < 2 {
+            eprintln!("Missing argument, try {} INDEX DATE", CMD_DUE);
+            process::exit(1)
+        }
 
+        let item = self.index_or_exit(&argv[0]);
+        let due = if argv[1].eq_ignore_ascii_case("none") {
+            None
+        } else {
+            let (due, words) = take_due_or_exit(&argv[1..]);
+            if words != argv.len() - 1 {
+                eprintln!("Invalid date: {}", argv[1..].join(" "));
+                process::exit(1)
             }
+            Some(due)
+        };
+        let ds = self.v.iter_mut().find(|ds| ds.index == item).unwrap();
+
+        ds.due = due;
+        ds.recur = ds.recur.as_deref().map(|rule| anchor_recur(rule, ds.due, ds.start));
+        self.changed = true;
+        let text = due.map(|d| format!("is due on {} ({})", due_date(d), due_string(d))).unwrap_or(String::from("has no due date"));
+        if use_color() {
+            println!("Task with id \x1B[92m{}\x1B[39m {}.", item, text);
+        } else {
+            println!("Task with id {} {}.", item, text);
+        }
 
-            if delete {
+    }
 
-                self.v.remove(pos.to_owned());
+    /// For internal use.
+    ///
+    /// Print the open tasks with a due date, with ```--days N``` only the overdue tasks and the
+    /// tasks of the next N days.
+    fn print_due(&self, argv: &[String]) {
+
+        let mut days: Option<u64> = None;
+        match argv {
+            [] => {}
+            [opt, n] if opt == "--days" && n.parse::<u64>().is_ok() => days = n.parse::<u64>().ok(),
+            _ => {
+                eprintln!("Unknown option, try {} [--days N]", CMD_DUE);
+                process::exit(1)
+            }
+        }
 
-                if io::stdout().is_terminal() {
-                    println!("Task with id \x1B[92m{}\x1B[39m was removed.", item);
-                } else {
-                    println!("Task with id {} was removed.", item);
-                }
+        let today = time_now() / 86_400 * 86_400;
+        let mut tasks: Vec<&TodoItem> = self.v.iter()
+            .filter(|ds| !ds.is_done && ds.due.is_some_and(|d| days.is_none_or(|n| d <= today + n * 86_400)))
+            .collect();
+        tasks.sort_by_key(|ds| ds.due);
 
-                let mut i: u32 = 0;
-                for ds in &mut self.v {
-                    i += 1;
-                    ds.index = i;
-                }
+        if tasks.is_empty() {
+            println!("There are no open tasks with a due date.");
+            return;
+        }
 
+        if use_color() {
+            println!("\n\x1B[1mDue Dates:\x1B[0m\n\x1B[1m----------\x1B[0m\n");
+        } else {
+            println!("\nDue Dates:\n----------\n");
+        }
+        for ds in tasks {
+            let due = ds.due.unwrap_or_default();
+            if use_color() && due < today {
+                println!("\x1B[92m{:2}.\x1B[39m \x1B[91m{}  {} ({})\x1B[39m", ds.index, due_date(due), ds.task, due_string(due));
+            } else if use_color() {
+                println!("\x1B[92m{:2}.\x1B[39m {}  {} ({})", ds.index, due_date(due), ds.task, due_string(due));
             } else {
-                println!("Aborted.");
+                println!("{:2}. {}  {} ({})", ds.index, due_date(due), ds.task, due_string(due));
             }
-
         }
+        println!();
 
     }
 
-    /// Reset the whole database and make a backup before resetting.
-    /// The user will be prompted for confirmation.
+    /// Show the recurring tasks, set the rule of a recurring task or stop the recurrence.
     ///
     /// **Command:**
     ///
-    /// ```todo reset```
-    pub fn reset(&mut self) {
+    /// ```todo recur```, ```todo recur 5 1w``` or ```todo recur 5 stop```
+    pub fn recur(&mut self, argv: &[String]) {
 
-        println!("Are you sure to reset the database, all entries will be lost (y|n)?");
-        let mut input = [0];
-        let mut stdin = io::stdin();//.read(&mut input);
-        let mut delete: bool = false;
+        if argv.is_empty() {
+            self.print_recur();
+            return;
+        }
+        if argv.len() < 2 {
+            eprintln!("Missing argument, try {} INDEX RULE", CMD_RECUR);
+            process::exit(1)
+        }
 
-        // prevent here, that during scripting the script waits for input
-        if stdin.is_terminal() {
+        let item = self.index_or_exit(&argv[0]);
+        let rule = match argv[1].to_ascii_lowercase().as_str() {
+            "stop" | "none" => None,
+            rule => Some(parse_recur_or_exit(rule)),
+        };
+        let ds = self.v.iter_mut().find(|ds| ds.index == item).unwrap();
 
-            let _ = stdin.read(&mut input);
+        ds.recur = rule.map(|rule| anchor_recur(&rule, ds.due, ds.start));
+        self.changed = true;
+        let text = ds.recur.as_deref().map(recur_string).unwrap_or(String::from("doesn't recur anymore"));
+        if use_color() {
+            println!("Task with id \x1B[92m{}\x1B[39m {}.", item, text);
+        } else {
+            println!("Task with id {} {}.", item, text);
+        }
 
-            loop {
+    }
 
-                match input[0] as char {
-                    'y' | 'Y' => {
-                        delete = true;
-                        break;
-                    }
-                    _ => break,
-                }
+    /// For internal use.
+    ///
+    /// Print the open recurring tasks with their rule.
+    fn print_recur(&self) {
+
+        let tasks: Vec<&TodoItem> = self.v.iter().filter(|ds| !ds.is_done && ds.recur.is_some()).collect();
+        if tasks.is_empty() {
+            println!("There are no recurring tasks.");
+            return;
+        }
+
+        if use_color() {
+            println!("\n\x1B[1mRecurring Tasks:\x1B[0m\n\x1B[1m----------------\x1B[0m\n");
+        } else {
+            println!("\nRecurring Tasks:\n----------------\n");
+        }
+        for ds in tasks {
+            let rule = recur_string(ds.recur.as_deref().unwrap_or_default());
+            let due = ds.due.map(|d| format!(" ({})", due_string(d))).unwrap_or_default();
+            if use_color() {
+                println!("\x1B[92m{:2}.\x1B[39m {}  {}{}", ds.index, ds.task, rule, due);
+            } else {
+                println!("{:2}. {}  {}{}", ds.index, ds.task, rule, due);
+            }
+        }
+        println!();
+
+    }
+
+    /// Edit the notes of a task in the editor of ```$VISUAL``` or ```$EDITOR```, the notes can have
+    /// more lines, e.g. links, commands or the notes of a meeting. A text after the ID is added as
+    /// new line without the editor, ```--clear``` removes the notes.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo note 5```, ```todo note 5 see the wiki page``` or ```todo note 5 --clear```
+    pub fn note(&mut self, argv: &[String]) {
+
+        let Some(arg) = argv.first() else {
+            eprintln!("Missing argument, try {} INDEX [TEXT|--clear]", CMD_NOTE);
+            process::exit(1)
+        };
+
+        let item = self.index_or_exit(arg);
+        let ds = self.v.iter_mut().find(|ds| ds.index == item).unwrap();
+        let notes = match argv.get(1).map(|a| a.as_str()) {
+            Some("--clear") => String::new(),
+            Some(_) if ds.notes.is_empty() => argv[1..].join(" "),
+            Some(_) => format!("{}\n{}", ds.notes, argv[1..].join(" ")),
+            None => edit_text(&ds.notes, format!("{}-{}-{}.md", APP_NAME, process::id(), item).as_str()),
+        };
+
+        if notes == ds.notes {
+            println!("The notes of the task with id {} are not changed.", item);
+            return;
+        }
+        ds.notes = notes;
+        self.changed = true;
+        let text = if ds.notes.is_empty() { "removed" } else { "saved" };
+        if use_color() {
+            println!("The notes of the task with id \x1B[92m{}\x1B[39m are {}.", item, text);
+        } else {
+            println!("The notes of the task with id {} are {}.", item, text);
+        }
+
+    }
+
+    /// Show all details of a task with its notes.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo show 5```
+    pub fn show(&self, argv: &[String]) {
+
+        let Some(arg) = argv.first() else {
+            eprintln!("Missing argument, try {} INDEX", CMD_SHOW);
+            process::exit(1)
+        };
+
+        let item = self.index_or_exit(arg);
+        let ds = self.v.iter().find(|ds| ds.index == item).unwrap();
+        let task_line = |uid: &String| match self.v.iter().find(|ds| ds.uid == *uid) {
+            Some(ds) => format!("{}. {}{}", ds.index, ds.task, if ds.is_done { " (done)" } else { "" }),
+            None => uid.to_string(),
+        };
+
+        let mut rows: Vec<(&str, String)> = Vec::new();
+        rows.push(("Id", format!("{} ({})", ds.uid, self.short_uid(&ds.uid))));
+        let state = match ds.completed {
+            Some(completed) if ds.is_done => format!("done {}", done_string(ds.start, completed)),
+            _ if ds.is_done => String::from("done"),
+            _ => String::from("open"),
+        };
+        rows.push(("State", state));
+        rows.push(("Started", date_time(ds.start)));
+        if let Some(p) = ds.priority {
+            rows.push(("Priority", p.to_string()));
+        }
+        if let Some(due) = ds.due {
+            rows.push(("Due", format!("{} ({})", due_date(due), due_string(due))));
+        }
+        if !ds.tags.is_empty() {
+            rows.push(("Tags", ds.tags.join(" ")));
+        }
+        if let Some(rule) = ds.recur.as_deref() {
+            rows.push(("Recurrence", recur_string(rule)));
+        }
+        if let Some(parent) = &ds.parent {
+            rows.push(("Parent", task_line(parent)));
+        }
+        for subtask in self.v.iter().filter(|c| c.parent.as_ref() == Some(&ds.uid) && c.index != ds.index) {
+            rows.push(("Subtask", task_line(&subtask.uid)));
+        }
+        for blocker in &ds.depends {
+            rows.push(("Blocked by", task_line(blocker)));
+        }
+        if !ds.sessions.is_empty() {
+            let tracked: u64 = ds.sessions.iter().map(|(start, end)| end.unwrap_or_else(time_now).saturating_sub(*start)).sum();
+            let running = if self.running() == Some(ds.index) { ", running" } else { "" };
+            rows.push(("Tracked", format!("{} in {} session(s){}", hours_string(tracked), ds.sessions.len(), running)));
+        }
+
+        if use_color() {
+            println!("\n\x1B[1m{}. {}\x1B[0m\n", ds.index, ds.task);
+        } else {
+            println!("\n{}. {}\n", ds.index, ds.task);
+        }
+        for (label, value) in rows {
+            println!("{:11} {}", format!("{}:", label), value);
+        }
+        if !ds.notes.is_empty() {
+            println!();
+            for line in ds.notes.lines() {
+                println!("    {}", line);
+            }
+        }
+        println!();
+
+    }
+
+    /// Start the time tracking of a task, only one task is tracked at a time, so the session of
+    /// another task is stopped.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo start 4```
+    pub fn start(&mut self, argv: &[String]) {
+
+        let Some(arg) = argv.first() else {
+            eprintln!("Missing argument, try {} INDEX", CMD_START);
+            process::exit(1)
+        };
+
+        let item = self.index_or_exit(arg);
+        if self.running() == Some(item) {
+            println!("The time of the task with id {} is already tracked.", item);
+            return;
+        }
+        if self.v.iter().any(|ds| ds.index == item && ds.is_done) {
+            eprintln!("Task with id {} is already done.", item);
+            process::exit(1)
+        }
+
+        self.stop_running();
+        let ds = self.v.iter_mut().find(|ds| ds.index == item).unwrap();
+        ds.sessions.push((time_now(), None));
+        self.changed = true;
+        if use_color() {
+            println!("Started the time tracking of the task with id \x1B[92m{}\x1B[39m.", item);
+        } else {
+            println!("Started the time tracking of the task with id {}.", item);
+        }
+
+    }
+
+    /// Stop the time tracking of the running task.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo stop```
+    pub fn stop(&mut self) {
+        if !self.stop_running() {
+            println!("No task is tracked at the moment.");
+        }
+    }
+
+    /// Show the tracked time per task, per day or per tag, a running session is counted until now.
+    /// The time of a task with more tags is counted for every tag. The range are whole days, with
+    /// ```--csv``` or ```--output``` the report is a CSV table for a timesheet.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo time```, ```todo time --by day --week``` or
+    /// ```todo time --by tag --from 2024-05-01 --to 2024-05-31 --output may.csv```
+    pub fn time(&self, argv: &[String]) {
+
+        let mut args = argv.to_vec();
+        let by = take_option(&mut args, &["-b", "--by"]).unwrap_or(String::from("task")).to_ascii_lowercase();
+        let output = take_option(&mut args, &["-o", "--output"]);
+        let csv = take_flag(&mut args, &["--csv"]) || output.is_some();
+        let today = time_now() / 86_400 * 86_400;
+        let mut from = take_option(&mut args, &["--from"]).map(|d| report_date_or_exit(&d)).unwrap_or(0);
+        let mut to = take_option(&mut args, &["--to"]).map(|d| report_date_or_exit(&d) + 86_400).unwrap_or(u64::MAX);
+        if take_flag(&mut args, &["--today"]) {
+            (from, to) = (today, today + 86_400);
+        }
+        if take_flag(&mut args, &["--week"]) {
+            from = today - weekday((today / 86_400) as i64) as u64 * 86_400;
+            to = from + 7 * 86_400;
+        }
+        if take_flag(&mut args, &["--month"]) {
+            let (y, m, _) = civil_from_secs(today);
+            let (next_y, next_m) = if m == 12 { (y + 1, 1) } else { (y, m + 1) };
+            from = days_from_civil(y, m, 1) as u64 * 86_400;
+            to = days_from_civil(next_y, next_m, 1) as u64 * 86_400;
+        }
+        if let Some(arg) = args.first() {
+            eprintln!("Unknown option: {}", arg);
+            process::exit(1)
+        }
+
+        // the parts of the sessions in the range, a session over midnight is split into the days
+        let now = time_now();
+        let mut parts: Vec<(&TodoItem, u64, u64)> = Vec::new();
+        for ds in &self.v {
+            for (start, end) in &ds.sessions {
+                let mut s = (*start).max(from);
+                let e = end.unwrap_or(now).min(to);
+                while s < e {
+                    let next_day = (s / 86_400 + 1) * 86_400;
+                    parts.push((ds, s, e.min(next_day)));
+                    s = next_day;
+                }
+            }
+        }
+
+        // the label in the report, the columns of the CSV table and the seconds
+        let mut rows: Vec<(String, Vec<String>, u64)> = Vec::new();
+        let mut add = |label: String, columns: Vec<String>, secs: u64| match rows.iter_mut().find(|r| r.0 == label) {
+            Some(row) => row.2 += secs,
+            None => rows.push((label, columns, secs)),
+        };
+        let header: &[&str] = match by.as_str() {
+            "task" => {
+                for (ds, s, e) in &parts {
+                    let columns = vec![ds.index.to_string(), ds.uid.clone(), ds.task.clone(), ds.tags.join(" ")];
+                    add(format!("{:2}. {}", ds.index, ds.task), columns, e - s);
+                }
+                &["id", "uid", "task", "tags", "hours"]
+            }
+            "day" => {
+                for (_, s, e) in &parts {
+                    add(date_string(*s), vec![date_string(*s)], e - s);
+                }
+                &["date", "hours"]
+            }
+            "tag" => {
+                for (ds, s, e) in &parts {
+                    if ds.tags.is_empty() {
+                        add(String::from("(no tag)"), vec![String::new()], e - s);
+                    }
+                    for tag in &ds.tags {
+                        add(tag.clone(), vec![tag.clone()], e - s);
+                    }
+                }
+                &["tag", "hours"]
+            }
+            _ => {
+                eprintln!("Unknown value for --by: {}, use task, day or tag", by);
+                process::exit(1)
+            }
+        };
+        if by != "task" {
+            rows.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+
+        if csv {
+            let table: Vec<Vec<String>> = rows.into_iter()
+                .map(|(_, mut columns, secs)| {
+                    columns.push(format!("{:.2}", secs as f64 / 3_600.0));
+                    columns
+                })
+                .collect();
+            let text = export_csv(header, &table);
+            match output {
+                Some(file_name) => {
+                    if let Err(err) = fs::write(&file_name, text) {
+                        eprintln!("Can't write file: {} (error: {})", file_name, err);
+                        process::exit(1)
+                    }
+                    println!("Exported {} row(s) into {}.", table.len(), file_name);
+                }
+                None => print!("{}", text),
+            }
+            return;
+        }
+
+        if rows.is_empty() {
+            println!("There is no tracked time.");
+            return;
+        }
+
+        let mut title = format!("Tracked time by {}", by);
+        if from > 0 || to < u64::MAX {
+            let first = if from > 0 { date_string(from) } else { String::from("the start") };
+            let last = if to < u64::MAX { date_string(to - 86_400) } else { date_string(today) };
+            title.push_str(format!(" from {} to {}", first, last).as_str());
+        }
+        let underline = "-".repeat(title.chars().count());
+        if use_color() {
+            println!("\n\x1B[1m{}\x1B[0m\n\x1B[1m{}\x1B[0m\n", title, underline);
+        } else {
+            println!("\n{}\n{}\n", title, underline);
+        }
+        for (label, _, secs) in &rows {
+            println!("{:>7}  {}", hours_string(*secs), label);
+        }
+        // not the sum of the rows, the time of a task with more tags is in more rows
+        let total: u64 = parts.iter().map(|(_, s, e)| e - s).sum();
+        println!("\n{:>7}  total", hours_string(total));
+        println!();
+
+    }
+
+    /// Edit an existing task, you need the ID for the task, get the ID's from ```todo list```
+    ///
+    /// **Command:**
+    ///
+    /// ```todo edit "Add an interactive mode to the todo source."```
+    ///
+    /// The tags in the new task replace the tags of the task, without a tag the tags are kept.
+    pub fn edit(&mut self, argv: &[String]) {
+
+        let Some(arg) = argv.first() else {
+            eprintln!("Missing argument, try {} INDEX TASK", CMD_EDIT);
+            process::exit(1)
+        };
+
+        let item = self.index_or_exit(arg);
+
+        let mut new_task = String::from("");
+        for i in &argv[1..] {
+            new_task.push_str(i);
+            new_task.push(' ');
+        }
+
+        // here I search for my internal task ID, it's the same as "Vector[i] + 1", but I want to try
+        // another way to get the right dataset
+        for ds in &mut self.v {
+
+            if ds.index == item {
+                let (task, tags) = split_tags(&new_task);
+                ds.task = task;
+                if !tags.is_empty() {
+                    ds.tags = tags;
+                }
+                self.changed = true;
+                if use_color() {
+                    println!("Task with id \x1B[92m{}\x1B[39m edited.", item);
+                } else {
+                    println!("Task with id {} edited.", item);
+                }
+                break;
+            }
+
+        }
+
+    }
+
+    /// Show the task list, with all ID's
+    ///
+    /// **Command:**
+    ///
+    /// ```todo list```, ```todo list --open```, ```todo list --done``` or ```todo list --all-lists```
+    ///
+    /// ```todo list --sort prio``` sorts the tasks by their priority and ```todo list --priority B```
+    /// shows only the tasks with the priority ```B``` or higher.
+    ///
+    /// ```todo list +release -@home``` shows only the tasks with the tag ```+release```, but
+    /// without the tag ```@home```.
+    pub fn list(&mut self, argv: &[String]) {
+
+        let mut opts = ListOptions {
+            filter: config().list_filter(),
+            sort: String::from("id"),
+            priority: None,
+            tags: Vec::new(),
+            without: Vec::new(),
+            uids: false,
+        };
+        let mut all_lists = false;
+        let mut args = argv.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-a" | "--all" => opts.filter = String::from("all"),
+                "-o" | "--open" => opts.filter = String::from("open"),
+                "-d" | "--done" => opts.filter = String::from("done"),
+                "--all-lists" => all_lists = true,
+                "-u" | "--uid" => opts.uids = true,
+                tag if is_tag(tag) => opts.tags.push(tag.to_string()),
+                tag if tag.starts_with('-') && is_tag(&tag[1..]) => opts.without.push(tag[1..].to_string()),
+                "-s" | "--sort" => {
+                    opts.sort = match args.next().map(|a| a.to_ascii_lowercase()) {
+                        Some(sort) if ["id", "prio", "priority", "due"].contains(&sort.as_str()) => sort,
+                        _ => {
+                            eprintln!("Missing or unknown value for {}, use id, prio or due", arg);
+                            process::exit(1)
+                        }
+                    };
+                }
+                "-p" | "--priority" => {
+                    opts.priority = match args.next() {
+                        Some(prio) => parse_priority_or_exit(prio),
+                        None => {
+                            eprintln!("Missing value for option {}", arg);
+                            process::exit(1)
+                        }
+                    };
+                }
+                _ => {
+                    eprintln!("Unknown option: {}", arg);
+                    process::exit(1)
+                }
+            }
+        }
+
+        if !all_lists {
+            self.print_tasks(&list_title(&config().list()), &opts);
+            return;
+        }
+
+        for name in list_names() {
+            let path = list_path(&name);
+            if path == self.storage.path() {
+                self.print_tasks(&list_title(&name), &opts);
+            } else {
+                TodoDatabase::load_from(list_storage(&path)).print_tasks(&list_title(&name), &opts);
+            }
+        }
+
+    }
+
+    /// For internal use.
+    ///
+    /// Print the tasks selected by the options under the ```title```.
+    fn print_tasks(&self, title: &str, opts: &ListOptions) {
+
+        if !self.v.is_empty() {
+
+            let underline = "-".repeat(title.chars().count());
+            if use_color() {
+                println!("\n\x1B[1m{}\x1B[0m\n\x1B[1m{}\x1B[0m\n", title, underline);
+            } else {
+                println!("\n{}\n{}\n", title, underline);
+            }
+
+            let mut ds_done: u32 = 0;
+            let mut ds_open: u32 = 0;
+
+            let mut tasks: Vec<&TodoItem> = self.v.iter().collect();
+            // tasks without priority or due date are the last ones, the sort keeps the order of the ID's
+            match opts.sort.as_str() {
+                "prio" | "priority" => tasks.sort_by_key(|ds| ds.priority.unwrap_or(char::MAX)),
+                "due" => tasks.sort_by_key(|ds| ds.due.unwrap_or(u64::MAX)),
+                _ => {}
+            }
+
+            // the subtasks follow their parent, the order of the sort is kept between the siblings
+            for (ds, depth) in task_tree(&tasks) {
+
+                let blockers = self.blockers(ds);
+                let hidden = (opts.filter == "open" && ds.is_done) || (opts.filter == "done" && !ds.is_done)
+                    || (opts.filter == "ready" && (ds.is_done || !blockers.is_empty()))
+                    || opts.priority.is_some_and(|min| ds.priority.is_none_or(|p| p > min))
+                    || opts.tags.iter().any(|t| !ds.tags.contains(t)) || opts.without.iter().any(|t| ds.tags.contains(t));
+                if hidden {
+                    if ds.is_done { ds_done += 1 } else { ds_open += 1 }
+                    continue;
+                }
+
+                let mut line = String::default();
+                let running = self.running() == Some(ds.index);
+
+                if use_color() {
+                    if ds.is_done {
+                        line.push_str("[\x1B[93mX\x1B[39m] ");
+                        ds_done += 1;
+                    } else if running {
+                        line.push_str("[\x1B[92m>\x1B[39m] ");
+                        ds_open += 1;
+                    } else {
+                        line.push_str("[ ] ");
+                        ds_open += 1;
+                    }
+                    line.push_str(format!("\x1B[92m{:2}.\x1B[39m ", ds.index).as_str());
+                    if opts.uids {
+                        line.push_str(format!("\x1B[2m{}\x1B[22m ", self.short_uid(&ds.uid)).as_str());
+                    }
+                    line.push_str("  ".repeat(depth).as_str());
+                    if let Some(p) = ds.priority {
+                        line.push_str(format!("{}({})\x1B[39m ", priority_color(p), p).as_str());
+                    }
+                    if ds.is_done {
+                        line.push_str(format!("\x1B[9m{}\x1B[0m", ds.task).as_str());
+                    } else if running {
+                        line.push_str(format!("\x1B[1m{}\x1B[22m", ds.task).as_str());
+                    } else if !blockers.is_empty() {
+                        line.push_str(format!("\x1B[2m{}\x1B[22m", ds.task).as_str());
+                    } else if ds.due.is_some_and(|due| due < time_now() / 86_400 * 86_400) {
+                        line.push_str(format!("\x1B[91m{}\x1B[39m", ds.task).as_str());
+                    } else {
+                        line.push_str(ds.task.as_str());
+                    }
+                    for tag in &ds.tags {
+                        let color = if tag.starts_with('+') { "\x1B[96m" } else { "\x1B[95m" };
+                        line.push_str(format!(" {}{}\x1B[39m", color, tag).as_str());
+                    }
+                } else {
+                    if ds.is_done {
+                        line.push_str("[X] ");
+                        ds_done += 1;
+                    } else if running {
+                        line.push_str("[>] ");
+                        ds_open += 1;
+                    } else {
+                        line.push_str("[ ] ");
+                        ds_open += 1;
+                    }
+                    line.push_str(format!("{:2}. ", ds.index).as_str());
+                    if opts.uids {
+                        line.push_str(format!("{} ", self.short_uid(&ds.uid)).as_str());
+                    }
+                    line.push_str("  ".repeat(depth).as_str());
+                    if let Some(p) = ds.priority {
+                        line.push_str(format!("({}) ", p).as_str());
+                    }
+                    line.push_str(ds.task.as_str());
+                    for tag in &ds.tags {
+                        line.push(' ');
+                        line.push_str(tag);
+                    }
+                }
+                if let Some(rule) = ds.recur.as_deref() {
+                    line.push_str(format!(" ({})", recur_string(rule)).as_str());
+                }
+                if !ds.notes.is_empty() {
+                    line.push_str(" [notes]");
+                }
+                if let Some((start, _)) = ds.sessions.last().filter(|_| running) {
+                    line.push_str(format!(" (tracked for {})", duration_string(time_diff(*start))).as_str());
+                }
+                // the progress of a parent, the finished and all direct subtasks
+                let subtasks: Vec<&TodoItem> = self.v.iter().filter(|c| c.parent.as_ref() == Some(&ds.uid) && c.index != ds.index).collect();
+                if !subtasks.is_empty() {
+                    line.push_str(format!(" ({}/{})", subtasks.iter().filter(|c| c.is_done).count(), subtasks.len()).as_str());
+                }
+                if !blockers.is_empty() {
+                    let ids: Vec<String> = blockers.iter().map(|i| i.to_string()).collect();
+                    if use_color() {
+                        line.push_str(format!(" \x1B[2m(blocked by {})\x1B[22m", ids.join(", ")).as_str());
+                    } else {
+                        line.push_str(format!(" (blocked by {})", ids.join(", ")).as_str());
+                    }
+                }
+                if let Some(completed) = ds.completed.filter(|_| ds.is_done) {
+                    line.push(' ');
+                    line.push_str(done_string(ds.start, completed).as_str());
+                }
+                if !ds.is_done {
+                    line.push(' ');
+                    line.push_str(since_string(ds.start).as_str());
+                    if let Some(due) = ds.due {
+                        if use_color() && due < time_now() / 86_400 * 86_400 {
+                            line.push_str(format!(" \x1B[91m({})\x1B[39m", due_string(due)).as_str());
+                        } else {
+                            line.push_str(format!(" ({})", due_string(due)).as_str());
+                        }
+                    }
+                }
+
+                println!("{}", line);
+
+            }
+            if ds_done == 1 {
+                if use_color() {
+                    println!("\nFound \x1B[92m{}\x1B[39m open task(s) and \x1B[93m{}\x1B[39m is finished.", ds_open, ds_done);
+                } else {
+                    println!("\nFound {} open task(s) and {} is finished.", ds_open, ds_done);
+                }
+            } else {
+                if use_color() {
+                    println!("\nFound \x1B[92m{}\x1B[39m open task(s) and \x1B[93m{}\x1B[39m are finished.", ds_open, ds_done);
+                } else {
+                    println!("\nFound {} open task(s) and {} are finished.", ds_open, ds_done);
+                }
+            }
+
+        } else {
+            println!("There are no tasks in the list.");
+        }
+
+        println!("({})", self.storage.path().display());
+        println!();
+
+    }
+
+    /// Show all tags of the tasks, with the number of open and all tasks.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo tags```
+    pub fn tags(&self) {
+
+        let mut tags: Vec<(&String, u32, u32)> = Vec::new();
+        for ds in &self.v {
+            for tag in &ds.tags {
+                let pos = match tags.iter().position(|(t, _, _)| *t == tag) {
+                    Some(pos) => pos,
+                    None => {
+                        tags.push((tag, 0, 0));
+                        tags.len() - 1
+                    }
+                };
+                if !ds.is_done {
+                    tags[pos].1 += 1;
+                }
+                tags[pos].2 += 1;
+            }
+        }
+
+        if tags.is_empty() {
+            println!("There are no tags in the list.");
+            return;
+        }
+
+        // the projects first, then the contexts
+        tags.sort();
+        let width = tags.iter().map(|(t, _, _)| t.chars().count()).max().unwrap_or_default();
+        for (tag, open, all) in tags {
+            if use_color() {
+                let color = if tag.starts_with('+') { "\x1B[96m" } else { "\x1B[95m" };
+                println!("{}{:width$}\x1B[39m  \x1B[92m{}\x1B[39m open, {} task(s)", color, tag, open, all, width = width);
+            } else {
+                println!("{:width$}  {} open, {} task(s)", tag, open, all, width = width);
+            }
+        }
+
+    }
+
+    /// Move a task into another list, the tasks of both lists get a new consecutive ID.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo move 2 work```
+    pub fn move_task(&mut self, argv: &[String]) {
+
+        if argv.len() < 2 {
+            eprintln!("Missing argument, try {} INDEX LIST", CMD_MOVE);
+            process::exit(1)
+        }
+
+        let item = self.index_or_exit(&argv[0]);
+
+        let name = check_list_name(&argv[1]);
+        let target = list_path(&name);
+        if target == self.storage.path() {
+            eprintln!("The task is already in the list {}.", name);
+            process::exit(1)
+        }
+        if !target.exists() {
+            eprintln!("The list {} doesn't exist, create it with '{} create {}'.", name, CMD_LISTS, name);
+            process::exit(1)
+        }
+
+        // the other list is saved first, so the task is never lost
+        let _lock = lock_file(&target, false);
+        let mut other = TodoDatabase::load_from(list_storage(&target));
+        other.migrate();
+        // the subtasks are moved with the task and get the next IDs in the other list, a permanent
+        // ID, which the other list already has, is replaced
+        let subtasks = self.descendants(item);
+        let moved: Vec<u32> = self.v.iter().map(|ds| ds.index).filter(|i| *i == item || subtasks.contains(i)).collect();
+        let first = other.get_highest_id() + 1;
+        let uids: Vec<(String, String)> = self.v.iter().filter(|ds| moved.contains(&ds.index))
+            .map(|ds| (ds.uid.clone(), if other.v.iter().any(|o| o.uid == ds.uid) { new_uid() } else { ds.uid.clone() }))
+            .collect();
+        let moved_uid = |old: &String| uids.iter().find(|(o, _)| o == old).map(|(_, new)| new.clone());
+        for (pos, ds) in self.v.iter().filter(|ds| moved.contains(&ds.index)).enumerate() {
+            let mut ds = ds.clone();
+            ds.parent = if ds.index == item { None } else { ds.parent.as_ref().and_then(moved_uid) };
+            // a dependency on a task in this list is lost
+            ds.depends = ds.depends.iter().filter_map(moved_uid).collect();
+            ds.index = first + pos as u32;
+            ds.uid = uids[pos].1.clone();
+            other.v.push(ds);
+        }
+        other.changed = true;
+        other.save(format!("{} {} {}", CMD_MOVE, item, name).as_str());
+
+        self.v.retain(|ds| !moved.contains(&ds.index));
+        self.renumber();
+        self.changed = true;
+
+        let new_item = first + moved.iter().position(|i| *i == item).unwrap_or_default() as u32;
+        let with = if subtasks.is_empty() { String::new() } else { format!(" with {} subtask(s)", subtasks.len()) };
+        if use_color() {
+            println!("Task with id \x1B[92m{}\x1B[39m{} moved to the list {} (new id {}).", item, with, name, new_item);
+        } else {
+            println!("Task with id {}{} moved to the list {} (new id {}).", item, with, name, new_item);
+        }
+
+    }
+
+    /// Remove one task, you need the ID for the task, get the ID's from ```todo list```.
+    /// The user will be prompted for confirmation.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo remove 2```
+    ///
+    /// The subtasks are removed with the task, with ```--keep-children``` they become subtasks
+    /// of the parent of the removed task.
+    pub fn remove(&mut self, argv: &[String]) {
+
+        let Some(arg) = argv.first() else {
+            eprintln!("Missing argument, try {} INDEX [--keep-children]", CMD_REMOVE);
+            process::exit(1)
+        };
+
+        let item = self.index_or_exit(arg);
+        let keep = argv[1..].iter().any(|a| a == "--keep-children");
+        let subtasks = if keep { Vec::new() } else { self.descendants(item) };
+
+        // this search is like the example of the official documentation
+        let pos = &self.v.iter().position(|i| i.index == item).unwrap();
+        let task = &self.v[pos.to_owned()].task;
+
+        let delete = if subtasks.is_empty() {
+            confirm(format!("Are you sure to delete this task (y|n)?\n-> {}", task).as_str())
+        } else {
+            confirm(format!("Are you sure to delete this task and its {} subtask(s) (y|n)?\n-> {}", subtasks.len(), task).as_str())
+        };
+
+        if delete {
+
+            let (uid, parent) = (self.v[pos.to_owned()].uid.clone(), self.v[pos.to_owned()].parent.clone());
+            for ds in self.v.iter_mut().filter(|ds| ds.parent.as_ref() == Some(&uid)) {
+                ds.parent = parent.clone();
+            }
+            self.v.retain(|ds| ds.index != item && !subtasks.contains(&ds.index));
+            self.changed = true;
+
+            if subtasks.is_empty() && use_color() {
+                println!("Task with id \x1B[92m{}\x1B[39m was removed.", item);
+            } else if subtasks.is_empty() {
+                println!("Task with id {} was removed.", item);
+            } else if use_color() {
+                println!("Task with id \x1B[92m{}\x1B[39m and {} subtask(s) were removed.", item, subtasks.len());
+            } else {
+                println!("Task with id {} and {} subtask(s) were removed.", item, subtasks.len());
+            }
+
+            self.renumber();
+
+        } else {
+            println!("Aborted.");
+        }
+
+    }
+
+    /// Export all tasks, the output is printed or written into a file.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo export --format json``` or ```todo export --format markdown --output TODO.md```
+    pub fn export(&self, argv: &[String]) {
+
+        let mut args = argv.to_vec();
+        let format = take_option(&mut args, &["-f", "--format"]).unwrap_or(String::from("json"));
+        let output = take_option(&mut args, &["-o", "--output"]);
+
+        let text = match format.to_ascii_lowercase().as_str() {
+            "json" => export_json(&self.v),
+            "todotxt" | "todo.txt" | "txt" => export_todotxt(&self.v),
+            "markdown" | "md" => export_markdown(&self.v),
+            "ical" | "ics" => export_ical(&self.v),
+            _ => {
+                eprintln!("Unknown export format: {}", format);
+                process::exit(1)
+            }
+        };
+
+        match output {
+            Some(file_name) => {
+                if let Err(err) = fs::write(&file_name, text) {
+                    eprintln!("Can't write file: {} (error: {})", file_name, err);
+                    process::exit(1)
+                }
+                println!("Exported {} task(s) into {}.", self.v.len(), file_name);
+            }
+            None => print!("{}", text),
+        }
+
+    }
+
+    /// Import tasks from a file (```-``` reads from stdin). The tasks are added to the list and
+    /// tasks with the same permanent ID are skipped, a file without IDs (e.g. Markdown) is
+    /// compared by the text. With ```--replace``` the whole list is replaced.
+    /// Files with the extension ```.txt``` are read as todo.txt, ```.md``` as Markdown, ```.ics```
+    /// as iCalendar and all others as JSON. A start or completion time in the future is set to now.
+    /// A finished duplicate marks the task in the list as done, so a checklist and the list can be
+    /// synced.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo import tasks.json``` or ```todo import --replace tasks.json```
+    pub fn import(&mut self, argv: &[String]) {
+
+        let mut args = argv.to_vec();
+        let format = take_option(&mut args, &["-f", "--format"]);
+        let replace = take_flag(&mut args, &["--replace"]);
+        take_flag(&mut args, &["--merge"]);
+
+        let file_name = match args.first() {
+            Some(file_name) => file_name.to_string(),
+            None => {
+                eprintln!("Missing file name, try {} [--merge|--replace] FILE", CMD_IMPORT);
+                process::exit(1)
+            }
+        };
+
+        let result = if file_name == "-" {
+            io::read_to_string(io::stdin())
+        } else {
+            fs::read_to_string(&file_name)
+        };
+        let text = match result {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("Can't read file: {} (error: {})", file_name, err);
+                process::exit(1)
+            }
+        };
+
+        // without a format the extension of the file is used
+        let format = format.unwrap_or_else(|| {
+            let ext = file_name.to_ascii_lowercase();
+            if ext.ends_with(".txt") {
+                String::from("todotxt")
+            } else if ext.ends_with(".md") || ext.ends_with(".markdown") {
+                String::from("markdown")
+            } else if ext.ends_with(".ics") {
+                String::from("ical")
+            } else {
+                String::from("json")
+            }
+        });
+        let result = match format.to_ascii_lowercase().as_str() {
+            "json" => import_json(&text),
+            "todotxt" | "todo.txt" | "txt" => import_todotxt(&text),
+            "markdown" | "md" => import_markdown(&text),
+            "ical" | "ics" => import_ical(&text),
+            _ => {
+                eprintln!("Unknown import format: {}", format);
+                process::exit(1)
+            }
+        };
+        let mut items = match result {
+            Ok(items) => items,
+            Err(err) => {
+                eprintln!("Can't import {} (error: {})", file_name, err);
+                process::exit(1)
+            }
+        };
+
+        // a task without a permanent ID in the file is compared by the text, it gets an ID here, so
+        // the links by the number of a task in the file are found
+        let without_uid: Vec<bool> = items.iter().map(|ds| ds.uid.is_empty()).collect();
+        for ds in items.iter_mut().filter(|ds| ds.uid.is_empty()) {
+            ds.uid = new_uid();
+        }
+        link_by_uid(&mut items.iter_mut().collect::<Vec<&mut TodoItem>>());
+
+        if replace {
+            self.v.clear();
+        }
+
+        let mut imported: u32 = 0;
+        let mut skipped: u32 = 0;
+        let mut updated: u32 = 0;
+        // the permanent IDs of the imported tasks in the file and in the database, for the links
+        let mut uids: Vec<(String, String)> = Vec::new();
+        let first = self.v.len();
+        let mut future: u32 = 0;
+        for (mut ds, without_uid) in items.into_iter().zip(without_uid) {
+            if clamp_to_now(&mut ds) {
+                future += 1;
+            }
+            // in merge mode a task with the same permanent ID is a duplicate, without an ID in the
+            // file a task with the same text, the tasks of the file are never duplicates of each other
+            if !replace {
+                let dup = self.v[..first].iter_mut()
+                    .find(|i| if without_uid { i.task.trim() == ds.task.trim() } else { i.uid == ds.uid });
+                if let Some(dup) = dup {
+                    uids.push((ds.uid.clone(), dup.uid.clone()));
+                    if ds.is_done && !dup.is_done {
+                        dup.is_done = true;
+                        dup.completed = ds.completed.or(Some(time_now()));
+                        updated += 1;
+                    } else {
+                        skipped += 1;
+                    }
+                    continue;
+                }
+            }
+            ds.index = self.get_highest_id() + 1;
+            let uid = if self.v.iter().any(|i| i.uid == ds.uid) { new_uid() } else { ds.uid.clone() };
+            uids.push((ds.uid.clone(), uid.clone()));
+            ds.uid = uid;
+            self.v.push(ds);
+            imported += 1;
+        }
+        let new_link = |old: &String| uids.iter().find(|(o, _)| o == old).map(|(_, new)| new.clone());
+        for ds in &mut self.v[first..] {
+            ds.parent = ds.parent.as_ref().and_then(new_link);
+            ds.depends = ds.depends.iter().filter_map(new_link).collect();
+        }
+        self.changed = replace || imported > 0 || updated > 0;
+
+        if use_color() {
+            println!("Imported \x1B[92m{}\x1B[39m task(s), marked {} as done and skipped {} duplicate(s).", imported, updated, skipped);
+        } else {
+            println!("Imported {} task(s), marked {} as done and skipped {} duplicate(s).", imported, updated, skipped);
+        }
+        if future > 0 {
+            println!("The times in the future of {} task(s) were set to now.", future);
+        }
+
+    }
+
+    /// Reset the whole database, the snapshot before saving is the backup.
+    /// The user will be prompted for confirmation.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo reset```
+    pub fn reset(&mut self) {
+
+        let delete = confirm("Are you sure to reset the database, all entries will be lost (y|n)?");
+
+        if delete {
+
+            self.v.clear();
+            self.changed = true;
+
+            if use_color() {
+                println!("\x1B[91mThe database was resetted, and is empty.\x1B[39m");
+            } else {
+                println!("The database was resetted, and is empty.");
+            }
+
+        } else {
+
+            println!("Aborted.");
+
+        }
+
+    }
+
+    /// Show all snapshots of the database, the newest first. The number is used by ```restore```.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo backups```
+    pub fn backups(&self) {
+
+        let backups = self.list_backups();
+        if backups.is_empty() {
+            println!("There are no backups in {}.", self.backup_dir().display());
+            return;
+        }
+
+        if use_color() {
+            println!("\n\x1B[1mBackups:\x1B[0m\n\x1B[1m--------\x1B[0m\n");
+        } else {
+            println!("\nBackups:\n--------\n");
+        }
+
+        for (i, path) in backups.iter().rev().enumerate() {
+            let items = self.load_backup(path);
+            let done = items.iter().filter(|ds| ds.is_done).count();
+            let time = fs::metadata(path).and_then(|m| m.modified()).ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|t| t.as_secs()).unwrap_or_default();
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            if use_color() {
+                println!("\x1B[92m{:2}.\x1B[39m {}  {:3} task(s), {} open, {} done  ({})",
+                    i + 1, date_time(time), items.len(), items.len() - done, done, name);
+            } else {
+                println!("{:2}. {}  {:3} task(s), {} open, {} done  ({})",
+                    i + 1, date_time(time), items.len(), items.len() - done, done, name);
+            }
+        }
+
+        println!();
+
+    }
+
+    /// Restore a snapshot of the database, the number from ```todo backups``` or the file name
+    /// of the snapshot. Without an argument the newest snapshot is restored. The current database
+    /// is saved as snapshot before, so a restore can be restored as well.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo restore``` or ```todo restore 3```
+    pub fn restore(&mut self, argv: &[String]) {
+
+        let backups = self.list_backups();
+
+        let path = match argv.first() {
+            Some(arg) => match arg.parse::<usize>() {
+                Ok(nr) if nr >= 1 && nr <= backups.len() => backups[backups.len() - nr].clone(),
+                Ok(_) => {
+                    eprintln!("There is no backup number {}, try {}.", arg, CMD_BACKUPS);
+                    process::exit(1)
+                }
+                Err(_) => {
+                    let in_dir = self.backup_dir().join(arg);
+                    if in_dir.exists() { in_dir } else { PathBuf::from(arg) }
+                }
+            },
+            // the backup file of older versions
+            None => match backups.last() {
+                Some(path) => path.clone(),
+                None => side_file(self.storage.path(), EXT_BACKUP),
+            },
+        };
+
+        if !path.exists() {
+            eprintln!("The backup {} does not exist.", path.display());
+            process::exit(1)
+        }
+
+        print!("Restoring database backup {}...", path.file_name().unwrap_or_default().to_string_lossy());
+
+        self.v = self.load_backup(&path);
+        self.bad_index.clear();
+        self.changed = true;
+
+        println!("done, {} task(s) restored.", self.v.len());
+
+    }
+
+    /// Undo the last change (or the last ```N``` changes) of the database, with ```--list``` the
+    /// recorded changes are shown. If the database was changed outside of todo, ```--force```
+    /// is needed, otherwise these changes would be lost.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo undo```, ```todo undo 3``` or ```todo undo --list```
+    pub fn undo(&mut self, argv: &[String]) {
+        self.replay(argv, true);
+    }
+
+    /// Redo the last undone change (or the last ```N``` changes), a new change of the database
+    /// removes all changes for redo.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo redo``` or ```todo redo 3```
+    pub fn redo(&mut self, argv: &[String]) {
+        self.replay(argv, false);
+    }
+
+    /// For internal use.
+    ///
+    /// The implementation of ```undo()``` and ```redo()```, the entries are moved from one stack
+    /// of the journal to the other.
+    fn replay(&mut self, argv: &[String], undo: bool) {
+
+        let mut args = argv.to_vec();
+        let list = take_flag(&mut args, &["-l", "--list"]);
+        let force = take_flag(&mut args, &["-f", "--force"]);
+        let count = args.first().and_then(|n| n.parse::<usize>().ok()).unwrap_or(1);
+
+        let mut journal = Journal::load(&side_file(self.storage.path(), EXT_JOURNAL));
+
+        if list {
+            self.list_journal(&journal);
+            return;
+        }
+
+        let (from, name) = if undo { (&mut journal.undo, "undo") } else { (&mut journal.redo, "redo") };
+        let newest = match from.last() {
+            Some(entry) => entry,
+            None => {
+                println!("Nothing to {}.", name);
+                return;
+            }
+        };
+
+        let current = if undo { &newest.after } else { &newest.before };
+        if !force && !is_state(&self.v, current) {
+            eprintln!("The database was changed outside of todo since '{}', use --force to {} anyway.", newest.command, name);
+            process::exit(1)
+        }
+
+        for _ in 0..count {
+            let entry = match if undo { journal.undo.pop() } else { journal.redo.pop() } {
+                Some(entry) => entry,
+                None => break,
+            };
+            self.v = state_items(if undo { &entry.before } else { &entry.after });
+            if use_color() {
+                println!("{}: \x1B[92m{}\x1B[39m ({})", if undo { "Undone" } else { "Redone" }, entry.command, date_time(entry.time));
+            } else {
+                println!("{}: {} ({})", if undo { "Undone" } else { "Redone" }, entry.command, date_time(entry.time));
+            }
+            if undo { journal.redo.push(entry) } else { journal.undo.push(entry) }
+        }
+
+        self.bad_index.clear();
+        self.journal = Some(journal);
+        self.changed = true;
+
+    }
+
+    /// For internal use.
+    ///
+    /// Show the recorded changes, the newest first.
+    fn list_journal(&self, journal: &Journal) {
+
+        if journal.undo.is_empty() && journal.redo.is_empty() {
+            println!("There are no recorded changes.");
+            return;
+        }
+
+        if use_color() {
+            println!("\n\x1B[1mHistory:\x1B[0m\n\x1B[1m--------\x1B[0m\n");
+        } else {
+            println!("\nHistory:\n--------\n");
+        }
+
+        for (i, e) in journal.redo.iter().enumerate() {
+            println!("redo {:2}. {}  {}", journal.redo.len() - i, date_time(e.time), e.command);
+        }
+        for (i, e) in journal.undo.iter().rev().enumerate() {
+            if use_color() {
+                println!("\x1B[92mundo {:2}.\x1B[39m {}  {}", i + 1, date_time(e.time), e.command);
+            } else {
+                println!("undo {:2}. {}  {}", i + 1, date_time(e.time), e.command);
+            }
+        }
+
+        println!();
+
+    }
+
+    /// Check the database for damaged lines, duplicate and zero indexes. Every problem is
+    /// reported with the line number, afterwards the user can repair the database in place.
+    /// The unreadable lines are moved into the side file ```todo.data.rejected``` and all
+    /// tasks get a new consecutive ID.
+    ///
+    /// **Command:**
+    ///
+    /// ```todo check``` or ```todo check --repair``` to repair without a question
+    pub fn check(&mut self, argv: &[String]) {
+
+        if self.damaged.is_empty() && self.bad_index.is_empty() {
+            println!("No problems found, the database is fine.");
+            return;
+        }
+
+        let mut problems: Vec<&DamagedLine> = self.damaged.iter().chain(self.bad_index.iter()).collect();
+        problems.sort_by_key(|dl| dl.nr);
+
+        for dl in problems {
+            if use_color() {
+                println!("{} \x1B[91m{}\x1B[39m: {}\n  -> {}", self.storage.record_name(), dl.nr, dl.reason, dl.line);
+            } else {
+                println!("{} {}: {}\n  -> {}", self.storage.record_name(), dl.nr, dl.reason, dl.line);
+            }
+        }
+        println!("\nFound {} unreadable line(s) and {} index problem(s).", self.damaged.len(), self.bad_index.len());
+
+        let repair = argv.iter().any(|a| a == "-r" || a == "--repair")
+            || confirm("Repair the database in place (y|n)?");
+
+        if repair {
+
+            if !self.damaged.is_empty() {
+
+                // the damaged lines are only removed from the database, if they are safe in the file
+                let rejected = side_file(self.storage.path(), EXT_REJECTED);
+                if let Err(err) = self.write_rejected(&rejected) {
+                    eprintln!("Can't write file: {} (error: {}), the database was not repaired.", rejected.display(), err);
+                    process::exit(1)
+                }
+
+                println!("Moved {} line(s) into {}.", self.damaged.len(), rejected.display());
+                self.damaged.clear();
+
+            }
+
+            if !self.bad_index.is_empty() {
+                self.renumber();
+                // the first task keeps a duplicate permanent ID, the others get a new one
+                for i in 1..self.v.len() {
+                    if self.v[..i].iter().any(|ds| ds.uid == self.v[i].uid) {
+                        self.v[i].uid = new_uid();
+                    }
+                }
+                self.bad_index.clear();
+                println!("All tasks got a new consecutive ID.");
+            }
+
+            self.changed = true;
+
+        } else {
+            println!("Aborted.");
+        }
+
+    }
+
+    /// For internal use.
+    ///
+    /// Append the damaged lines with their reason to the file of the rejected lines.
+    fn write_rejected(&self, path: &Path) -> io::Result<()> {
+        let mut text = format!("# rejected by {} at {}\n", CMD_CHECK, time_now());
+        for dl in &self.damaged {
+            text.push_str(format!("# {} {}: {}\n{}\n", self.storage.record_name(), dl.nr, dl.reason, dl.line).as_str());
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(text.as_bytes())?;
+        file.sync_all()
+    }
+
+    /// For internal use.
+    ///
+    /// The function do what the name says, looks for the highest ID in the database.
+    fn get_highest_id(&self) -> u32 {
+        let mut id: u32 = 0;
+        for ds in &self.v {
+            if ds.index > id { id = ds.index; }
+        }
+        id
+    }
+
+    /// For internal use.
+    ///
+    /// The ID of the task, whose time is tracked at the moment.
+    fn running(&self) -> Option<u32> {
+        self.v.iter().find(|ds| ds.sessions.iter().any(|(_, end)| end.is_none())).map(|ds| ds.index)
+    }
+
+    /// For internal use.
+    ///
+    /// Stop the running session, the result is false, if no task is tracked.
+    fn stop_running(&mut self) -> bool {
+        let Some(ds) = self.v.iter_mut().find(|ds| ds.sessions.iter().any(|(_, end)| end.is_none())) else {
+            return false;
+        };
+        let now = time_now();
+        let mut tracked = 0;
+        for (start, end) in ds.sessions.iter_mut().filter(|(_, end)| end.is_none()) {
+            *end = Some(now.max(*start));
+            tracked += now.saturating_sub(*start);
+        }
+        // a task started by mistake doesn't leave an empty session
+        ds.sessions.retain(|(start, end)| end.is_none_or(|end| end > *start));
+        self.changed = true;
+        if use_color() {
+            println!("Stopped the time tracking of the task with id \x1B[92m{}\x1B[39m after {}.", ds.index, duration_string(tracked));
+        } else {
+            println!("Stopped the time tracking of the task with id {} after {}.", ds.index, duration_string(tracked));
+        }
+        true
+    }
+
+    /// For internal use.
+    ///
+    /// Give all tasks a new consecutive ID, the links by the permanent IDs are kept. A subtask of a
+    /// removed task becomes a task without parent, a dependency on a removed task is removed.
+    fn renumber(&mut self) {
+        let uids: Vec<String> = self.v.iter().map(|ds| ds.uid.clone()).collect();
+        for (i, ds) in self.v.iter_mut().enumerate() {
+            ds.index = i as u32 + 1;
+            ds.parent = ds.parent.take().filter(|p| uids.contains(p));
+            ds.depends.retain(|d| uids.contains(d));
+        }
+    }
+
+    /// For internal use.
+    ///
+    /// The permanent ID of an existing task, the tasks are linked by it.
+    fn uid_of(&self, item: u32) -> String {
+        self.v.iter().find(|ds| ds.index == item).map(|ds| ds.uid.clone()).unwrap_or_default()
+    }
+
+    /// For internal use.
+    ///
+    /// The IDs of the open tasks, which block a task.
+    fn blockers(&self, ds: &TodoItem) -> Vec<u32> {
+        ds.depends.iter().filter_map(|d| self.v.iter().find(|b| b.uid == *d && !b.is_done)).map(|b| b.index).collect()
+    }
+
+    /// For internal use.
+    ///
+    /// Check, if a task depends on another task, directly or by the dependencies of its dependencies.
+    fn depends_on(&self, item: u32, other: u32) -> bool {
+        let other = self.uid_of(other);
+        let mut seen: Vec<&String> = Vec::new();
+        let mut next = vec![self.uid_of(item)];
+        while let Some(uid) = next.pop() {
+            for ds in self.v.iter().filter(|ds| ds.uid == uid) {
+                for d in &ds.depends {
+                    if *d == other {
+                        return true;
+                    }
+                    if !seen.contains(&d) {
+                        seen.push(d);
+                        next.push(d.clone());
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// For internal use.
+    ///
+    /// The ID of an existing task, the argument is the number in the list or the start of the
+    /// permanent ID (at least 4 characters). A number is never a permanent ID, so a removed number
+    /// can't select another task, the start of a permanent ID needs a letter or a ```#``` like
+    /// ```#1234```. An unknown or an ambiguous ID is an error.
+    fn find_index(&self, arg: &str) -> Result<u32, String> {
+        let prefix = match arg.strip_prefix('#') {
+            Some(prefix) => prefix.to_ascii_lowercase(),
+            None if arg.chars().any(|c| c.is_ascii_alphabetic()) => arg.to_ascii_lowercase(),
+            None => {
+                return match arg.parse::<u32>() {
+                    Ok(item) if self.v.iter().any(|ds| ds.index == item) => Ok(item),
+                    _ => Err(format!("Task with id {} not found.", arg)),
+                };
+            }
+        };
+        let found: Vec<u32> = self.v.iter().filter(|ds| prefix.len() >= 4 && ds.uid.starts_with(&prefix)).map(|ds| ds.index).collect();
+        match found.as_slice() {
+            [item] => Ok(*item),
+            [] => Err(format!("Task with id {} not found.", arg)),
+            _ => Err(format!("The id {} is ambiguous, it matches {} tasks.", arg, found.len())),
+        }
+    }
+
+    /// For internal use.
+    ///
+    /// The ID of an existing task like ```find_index()```, the program stops with an unknown or an
+    /// ambiguous ID.
+    fn index_or_exit(&self, arg: &str) -> u32 {
+        self.find_index(arg).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(1)
+        })
+    }
+
+    /// For internal use.
+    ///
+    /// The shortest start of a permanent ID (at least 6 characters), which no other task has. It
+    /// has a letter, so it's never read as the number of a task.
+    fn short_uid<'a>(&self, uid: &'a str) -> &'a str {
+        let mut len = 6.min(uid.len());
+        let ambiguous = |start: &str| !start.chars().any(|c| c.is_ascii_alphabetic())
+            || self.v.iter().any(|ds| ds.uid != uid && ds.uid.starts_with(start));
+        while len < uid.len() && ambiguous(&uid[..len]) {
+            len += 1;
+        }
+        &uid[..len]
+    }
+
+    /// For internal use.
+    ///
+    /// The IDs of all subtasks of a task, with the subtasks of the subtasks.
+    fn descendants(&self, item: u32) -> Vec<u32> {
+        let mut found: Vec<u32> = Vec::new();
+        let mut parents = vec![self.uid_of(item)];
+        while let Some(p) = parents.pop() {
+            for ds in self.v.iter().filter(|ds| ds.parent.as_ref() == Some(&p)) {
+                if ds.index != item && !found.contains(&ds.index) {
+                    found.push(ds.index);
+                    parents.push(ds.uid.clone());
+                }
+            }
+        }
+        found
+    }
+
+}
+
+/// For internal use.
+///
+/// Sort the tasks as a tree, every subtask follows its parent, the result are the tasks with
+/// their depth in the tree. A task, whose parent isn't in the list, is at the top level.
+pub(crate) fn task_tree<'a>(tasks: &[&'a TodoItem]) -> Vec<(&'a TodoItem, usize)> {
+
+    let mut tree: Vec<(&TodoItem, usize)> = Vec::new();
+    let mut seen = vec![false; tasks.len()];
+    let roots = (0..tasks.len()).filter(|&i| tasks[i].parent.as_ref().is_none_or(|p| !tasks.iter().any(|t| t.uid == *p)));
+
+    // the tasks of a damaged tree (a parent of itself) have no root, they are shown at the end
+    for root in roots.collect::<Vec<usize>>().into_iter().chain(0..tasks.len()) {
+        let mut stack = vec![(root, 0)];
+        while let Some((i, depth)) = stack.pop() {
+            if seen[i] {
+                continue;
+            }
+            seen[i] = true;
+            tree.push((tasks[i], depth));
+            for c in (0..tasks.len()).rev().filter(|&c| !seen[c] && tasks[c].parent.as_ref() == Some(&tasks[i].uid)) {
+                stack.push((c, depth + 1));
+            }
+        }
+    }
+    tree
+
+}
+
+/// Take the advisory lock for the database, so concurrent invocations of todo wait until the
+/// others have saved their changes. The lock is released when the returned ```File``` is dropped.
+pub fn lock_database() -> File {
+    lock_file(get_storage().path(), true)
+}
+
+/// For internal use.
+///
+/// Take the advisory lock for a database file, without ```wait``` the program stops if the
+/// database is in use.
+fn lock_file(path: &Path, wait: bool) -> File {
+
+    let lock_name = side_file(path, EXT_LOCK);
+
+    let file = match OpenOptions::new().create(true).truncate(false).write(true).open(&lock_name) {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("Can't create lock file: {} (error: {})", lock_name.display(), err);
+            process::exit(1)
+        }
+    };
+
+    let result = match file.try_lock() {
+        Err(TryLockError::WouldBlock) if !wait => {
+            eprintln!("The database {} is in use by another todo process, try it again.", path.display());
+            process::exit(1)
+        }
+        Err(TryLockError::WouldBlock) => {
+            eprintln!("The database is in use by another todo process, waiting...");
+            file.lock()
+        }
+        Err(TryLockError::Error(err)) => Err(err),
+        Ok(()) => Ok(()),
+    };
+    if let Err(err) = result {
+        eprintln!("Can't lock the database: {} (error: {})", lock_name.display(), err);
+        process::exit(1)
+    }
+
+    file
+
+}
+
+/// For internal use.
+///
+/// Open the storage backend of the selected list or the database file of the configuration.
+fn get_storage() -> Box<dyn Storage> {
+    if let Some(path) = config().db() {
+        return list_storage(&path);
+    }
+    let name = check_list_name(&config().list());
+    if name == DEFAULT_LIST && !config().global() {
+        if let Some(path) = find_project_file() {
+            return project_storage(&path);
+        }
+    }
+    let path = list_path(&name);
+    if name != DEFAULT_LIST && !path.exists() {
+        eprintln!("The list {} doesn't exist, create it with '{} create {}'.", name, CMD_LISTS, name);
+        process::exit(1)
+    }
+    list_storage(&path)
+}
+
+/// For internal use.
+///
+/// Open the storage backend for a database file, the directory is created if needed.
+fn list_storage(path: &Path) -> Box<dyn Storage> {
+
+    let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    if !dir.exists() {
+        if let Err(err) = create_dir_all(dir) {
+            eprintln!("Can't create directory: {} (error: {})", dir.display(), err);
+            process::exit(1)
+        }
+    }
+
+    match open_storage(path, &config().storage()) {
+        Ok(storage) => storage,
+        Err(err) => {
+            eprintln!("Can't open the database (error: {})", err);
+            process::exit(1)
+        }
+    }
+
+}
+
+/// For internal use.
+///
+/// Search the task list of a project like git searches ```.git```, in the working directory and
+/// all parent directories.
+fn find_project_file() -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    cwd.ancestors().map(|dir| dir.join(PROJECT_FILE)).find(|path| path.is_file())
+}
+
+/// For internal use.
+///
+/// Open the task list of a project, it's always a CSV file, so it can be committed with the project.
+fn project_storage(path: &Path) -> Box<dyn Storage> {
+    match open_storage(path, "csv") {
+        Ok(storage) => storage,
+        Err(err) => {
+            eprintln!("Can't open the database (error: {})", err);
+            process::exit(1)
+        }
+    }
+}
+
+/// Create the task list of a project in the working directory.
+///
+/// **Command:**
+///
+/// ```todo init```
+pub fn init() {
+
+    let path = match env::current_dir() {
+        Ok(cwd) => cwd.join(PROJECT_FILE),
+        Err(err) => {
+            eprintln!("Can't get the working directory (error: {})", err);
+            process::exit(1)
+        }
+    };
+    if path.exists() {
+        eprintln!("The task list {} already exists.", path.display());
+        process::exit(1)
+    }
+
+    if let Err(err) = project_storage(&path).save(&[]) {
+        eprintln!("Can't create the task list: {} (error: {})", path.display(), err);
+        process::exit(1)
+    }
+    println!("Created the task list {}, it is used in this directory and all subdirectories.", path.display());
+    println!("The journal, lock file and backups are stored in {}.*, e.g. add it to .gitignore.", PROJECT_FILE);
+
+}
+
+/// For internal use.
+///
+/// The database file of a list, the default list is stored in the data directory and the named
+/// lists in the subdirectory ```lists```. An existing list keeps the extension of its backend.
+fn list_path(name: &str) -> PathBuf {
+
+    let dir = config().data_dir().unwrap_or_else(get_app_dir);
+    let file_name = storage_file_name(&config().storage());
+    if name.is_empty() || name == DEFAULT_LIST {
+        return dir.join(file_name);
+    }
+
+    let lists_dir = dir.join(APP_LISTS);
+    if let Some(path) = list_files(&lists_dir).into_iter().find(|p| p.file_stem().unwrap_or_default() == name) {
+        return path;
+    }
+    let ext = Path::new(file_name).extension().unwrap_or_default().to_string_lossy().to_string();
+    lists_dir.join(format!("{}.{}", name, ext))
+
+}
+
+/// For internal use.
+///
+/// The database files in the directory of the named lists, the side files are skipped.
+fn list_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = match read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_file()).collect(),
+        Err(_) => Vec::new(),
+    };
+    files.retain(|p| matches!(p.extension().unwrap_or_default().to_str(), Some("data" | "json" | "txt" | "sqlite" | "db")));
+    files.sort();
+    files
+}
+
+/// For internal use.
+///
+/// The names of all lists, the default list is the first.
+fn list_names() -> Vec<String> {
+    let dir = config().data_dir().unwrap_or_else(get_app_dir).join(APP_LISTS);
+    let mut names = vec![String::from(DEFAULT_LIST)];
+    names.extend(list_files(&dir).iter().map(|p| p.file_stem().unwrap_or_default().to_string_lossy().to_string()));
+    names
+}
+
+/// For internal use.
+///
+/// The title of the task list, with the name of a named list.
+fn list_title(name: &str) -> String {
+    if name.is_empty() || name == DEFAULT_LIST {
+        String::from("Task List:")
+    } else {
+        format!("Task List {}:", name)
+    }
+}
+
+/// For internal use.
+///
+/// Check the name of a list, only letters, digits, ```-``` and ```_``` are allowed.
+fn check_list_name(name: &str) -> String {
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        eprintln!("Invalid list name: {} (use only letters, digits, '-' and '_')", name);
+        process::exit(1)
+    }
+    name.to_string()
+}
+
+/// Show, create, rename or delete the named lists.
+///
+/// **Command:**
+///
+/// ```todo lists```, ```todo lists create work```, ```todo lists rename work job``` or
+/// ```todo lists delete job```
+pub fn lists(argv: &[String]) {
+
+    let sub = argv.first().map(|a| a.to_ascii_lowercase()).unwrap_or_default();
+    match (sub.as_str(), argv.len()) {
+
+        ("", _) => {
+            if use_color() {
+                println!("\n\x1B[1mLists:\x1B[0m\n\x1B[1m------\x1B[0m\n");
+            } else {
+                println!("\nLists:\n------\n");
+            }
+            let selected = list_path(&config().list());
+            for name in list_names() {
+                let path = list_path(&name);
+                let items = list_storage(&path).load().unwrap_or_default().into_iter().filter_map(|r| r.item.ok()).collect::<Vec<TodoItem>>();
+                let done = items.iter().filter(|ds| ds.is_done).count();
+                let mark = if path == selected { "*" } else { " " };
+                if use_color() {
+                    println!("{} \x1B[92m{:12}\x1B[39m {:3} task(s), {} open, {} done", mark, name, items.len(), items.len() - done, done);
+                } else {
+                    println!("{} {:12} {:3} task(s), {} open, {} done", mark, name, items.len(), items.len() - done, done);
+                }
+            }
+            println!();
+        }
+
+        ("create", 2) => {
+            let name = check_list_name(&argv[1]);
+            let path = list_path(&name);
+            if path.exists() {
+                eprintln!("The list {} already exists.", name);
+                process::exit(1)
+            }
+            if let Err(err) = list_storage(&path).save(&[]) {
+                eprintln!("Can't create the list: {} (error: {})", path.display(), err);
+                process::exit(1)
+            }
+            println!("List {} created.", name);
+        }
+
+        ("rename", 3) => {
+            let (old, new) = (check_list_name(&argv[1]), check_list_name(&argv[2]));
+            let (old_path, new_path) = (list_path(&old), list_path(&new));
+            if old == DEFAULT_LIST || new == DEFAULT_LIST || !old_path.exists() || new_path.exists() {
+                eprintln!("Can't rename the list {} to {}, the list must exist and the new name must be unused.", old, new);
+                process::exit(1)
+            }
+            let new_path = new_path.with_extension(old_path.extension().unwrap_or_default());
+            let lock = lock_file(&old_path, false);
+            let mut files = vec![(old_path.clone(), new_path.clone()), (side_file(&old_path, EXT_JOURNAL), side_file(&new_path, EXT_JOURNAL)),
+                (side_file(&old_path, EXT_BACKUP), side_file(&new_path, EXT_BACKUP))];
+            // the snapshots are named after the list
+            for backup in TodoDatabase::new(list_storage(&old_path)).list_backups() {
+                let name = backup.file_name().unwrap_or_default().to_string_lossy().to_string();
+                let renamed = backup.with_file_name(format!("{}{}", new, &name[old.len()..]));
+                files.push((backup, renamed));
+            }
+            for (from, to) in files {
+                if from.exists() {
+                    if let Err(err) = fs::rename(&from, &to) {
+                        eprintln!("Can't rename {} (error: {})", from.display(), err);
+                        process::exit(1)
+                    }
+                }
+            }
+            drop(lock);
+            let _ = fs::remove_file(side_file(&old_path, EXT_LOCK));
+            println!("List {} renamed to {}.", old, new);
+        }
+
+        ("delete", 2) => {
+            let name = check_list_name(&argv[1]);
+            let path = list_path(&name);
+            if name == DEFAULT_LIST || !path.exists() {
+                eprintln!("Can't delete the list {}, only existing named lists can be deleted.", name);
+                process::exit(1)
+            }
+            let lock = lock_file(&path, false);
+            let todo_db = TodoDatabase::load_from(list_storage(&path));
+            if !confirm(format!("Are you sure to delete the list {} with {} task(s) (y|n)?", name, todo_db.v.len()).as_str()) {
+                println!("Aborted.");
+                return;
+            }
+            // after todo lists create NAME the last snapshot can be restored with todo --list NAME restore
+            todo_db.snapshot();
+            for file in [path.clone(), side_file(&path, EXT_JOURNAL), side_file(&path, EXT_BACKUP)] {
+                if file.exists() {
+                    if let Err(err) = fs::remove_file(&file) {
+                        eprintln!("Can't delete {} (error: {})", file.display(), err);
+                        process::exit(1)
+                    }
+                }
+            }
+            drop(lock);
+            let _ = fs::remove_file(side_file(&path, EXT_LOCK));
+            println!("List {} deleted, the last snapshot is kept in {}.", name, todo_db.backup_dir().display());
+        }
+
+        _ => {
+            eprintln!("Unknown or incomplete command, try {} [create NAME|rename OLD NEW|delete NAME]", CMD_LISTS);
+            process::exit(1)
+        }
+
+    }
+
+}
+
+/// For internal use.
+///
+/// The application directory of the database, ```$XDG_DATA_HOME/todo``` (```~/.local/share/todo```)
+/// or ```%LOCALAPPDATA%\todo``` on Windows. The legacy directory ```~/.r_todo``` is moved there once,
+/// if that is not possible the legacy directory is used further on.
+pub(crate) fn get_app_dir() -> PathBuf {
+    static APP_DIR: OnceLock<PathBuf> = OnceLock::new();
+    APP_DIR.get_or_init(|| {
+        let app_dir = get_os_data_dir().join(APP_NAME);
+        let legacy = get_os_home_dir().join(APP_SUBDIR);
+        if app_dir.exists() || !legacy.is_dir() {
+            return app_dir;
+        }
+        let result = create_dir_all(get_os_data_dir()).and_then(|_| fs::rename(&legacy, &app_dir));
+        if let Err(err) = result {
+            if !app_dir.exists() {
+                eprintln!("Can't move {} to {} (error: {})", legacy.display(), app_dir.display(), err);
+                return legacy;
+            }
+        } else {
+            eprintln!("Moved the ToDo directory {} to {}", legacy.display(), app_dir.display());
+        }
+        // the configuration file was stored next to the database
+        let (old_config, new_config) = (app_dir.join(APP_CONFIG), get_os_config_dir().join(APP_NAME).join(APP_CONFIG));
+        if old_config.exists() && !new_config.exists() {
+            let result = create_dir_all(get_os_config_dir().join(APP_NAME)).and_then(|_| fs::rename(&old_config, &new_config));
+            if let Err(err) = result {
+                eprintln!("Can't move {} to {} (error: {})", old_config.display(), new_config.display(), err);
+            }
+        }
+        app_dir
+    }).clone()
+}
+
+/// For internal use.
+///
+/// The directory of the configuration file, ```$XDG_CONFIG_HOME/todo``` (```~/.config/todo```)
+/// or ```%LOCALAPPDATA%\todo``` on Windows. While the legacy directory ```~/.r_todo``` is used,
+/// the configuration file is stored there, too.
+pub(crate) fn get_config_dir() -> PathBuf {
+    let app_dir = get_app_dir();
+    if app_dir.ends_with(APP_SUBDIR) {
+        app_dir
+    } else {
+        get_os_config_dir().join(APP_NAME)
+    }
+}
+
+/// For internal use.
+///
+/// The time stamp and the number of a snapshot like ```todo-20240501-120000-2.data```, the
+/// snapshots of the same second are numbered and compared as number, so ```-10``` follows ```-9```.
+fn backup_order(path: &Path, stem: &str) -> Option<(String, u32)> {
+    let name = path.file_stem()?.to_string_lossy().to_string();
+    let rest = name.strip_prefix(stem)?.strip_prefix('-')?;
+    let stamp = rest.get(..15)?.to_string();
+    let nr = match rest[15..].strip_prefix('-') {
+        Some(nr) => nr.parse::<u32>().ok()?,
+        None => 1,
+    };
+    Some((stamp, nr))
+}
 
-            }
+/// For internal use.
+///
+/// The name of a side file of the database, the extension is appended to the file name.
+fn side_file(path: &Path, ext: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(ext);
+    PathBuf::from(name)
+}
 
-        }
+/// For internal use.
+///
+/// Very easy function to get the user dir depended on the operating system
+/// you work with this program on the command line, so the environment
+/// variables will be set.
+fn get_os_home_dir() -> PathBuf {
+    #[cfg(target_os="windows")]
+    let d = env::var("LOCALAPPDATA").unwrap_or_else(|err| {
+        eprintln!("could not find %LOCALAPPDATA%: {}", err);
+        String::from(".")
+    });
+    #[cfg(not(target_os="windows"))]
+    let d = env::var("HOME").unwrap_or_else(|err| {
+        eprintln!("could not find %HOME: {}", err);
+        String::from(".")
+    });
+    PathBuf::from(d)
+}
 
-        if delete {
+/// For internal use.
+///
+/// The base directory for data files, ```$XDG_DATA_HOME``` or ```~/.local/share```.
+fn get_os_data_dir() -> PathBuf {
+    #[cfg(target_os="windows")]
+    let d = get_os_home_dir();
+    #[cfg(not(target_os="windows"))]
+    let d = get_xdg_dir("XDG_DATA_HOME").unwrap_or_else(|| get_os_home_dir().join(".local").join("share"));
+    d
+}
 
-            let app_dir = get_os_data_dir();
-            let old_db = Path::new(&app_dir).join(APP_SUBDIR).join(APP_DATABASE);
-            let bak_db = Path::new(&app_dir).join(APP_SUBDIR).join(APP_BACKUP);
+/// For internal use.
+///
+/// The base directory for configuration files, ```$XDG_CONFIG_HOME``` or ```~/.config```.
+fn get_os_config_dir() -> PathBuf {
+    #[cfg(target_os="windows")]
+    let d = get_os_home_dir();
+    #[cfg(not(target_os="windows"))]
+    let d = get_xdg_dir("XDG_CONFIG_HOME").unwrap_or_else(|| get_os_home_dir().join(".config"));
+    d
+}
 
-            let _cp = match copy(&old_db, &bak_db) {
-                Err(err) => {
-                    eprintln!("Can't copy database: {} (error: {})", old_db.display(), err);
-                    process::exit(1)
-                }
-                Ok(cp) => cp,
-            };
+/// For internal use.
+///
+/// A XDG base directory from the environment, relative paths are invalid and ignored.
+#[cfg(not(target_os="windows"))]
+fn get_xdg_dir(name: &str) -> Option<PathBuf> {
+    env::var(name).ok().map(PathBuf::from).filter(|d| d.is_absolute())
+}
 
-            self.v.clear();
+/// For internal use.
+///
+/// Remove the option with one of the ```names``` and its value from the arguments and
+/// return the value, e.g. ```--format json```.
+fn take_option(argv: &mut Vec<String>, names: &[&str]) -> Option<String> {
+    let pos = argv.iter().position(|a| names.contains(&a.as_str()))?;
+    if pos + 1 >= argv.len() {
+        eprintln!("Missing value for option {}", argv[pos]);
+        process::exit(1)
+    }
+    let value = argv.remove(pos + 1);
+    argv.remove(pos);
+    Some(value)
+}
 
-            if io::stdout().is_terminal() {
-                println!("\x1B[91mThe database was resetted, and is empty.\x1B[39m");
-            } else {
-                println!("The database was resetted, and is empty.");
-            }
+/// For internal use.
+///
+/// Remove the flag with one of the ```names``` from the arguments, returns ```true``` if it was found.
+fn take_flag(argv: &mut Vec<String>, names: &[&str]) -> bool {
+    let len = argv.len();
+    argv.retain(|a| !names.contains(&a.as_str()));
+    argv.len() != len
+}
 
-        } else {
+/// For internal use.
+///
+/// Ask the user a yes or no question, the prompt is printed as it is. During scripting (stdin is not a terminal) the
+/// question is always answered with no, so a script never waits for input. With the key ```confirm = false```
+/// of the configuration the question is always answered with yes.
+fn confirm(prompt: &str) -> bool {
+    println!("{}", prompt);
+    if !config().confirm() {
+        return true;
+    }
+    let mut input = [0];
+    let mut stdin = io::stdin();
+    if stdin.is_terminal() && stdin.read(&mut input).is_ok() {
+        return matches!(input[0] as char, 'y' | 'Y');
+    }
+    false
+}
 
-            println!("Aborted.");
+/// For internal use.
+///
+/// Edit a text in the editor of ```$VISUAL``` or ```$EDITOR``` (```vi``` without both). The text is
+/// written into a new temporary file with the ```name```, which is removed after the editor is
+/// closed. The program stops, if the editor can't be started or fails.
+fn edit_text(text: &str, name: &str) -> String {
+
+    let editor = ["VISUAL", "EDITOR"].iter()
+        .find_map(|var| env::var(var).ok().filter(|e| !e.trim().is_empty()))
+        .unwrap_or(String::from("vi"));
+    let path = env::temp_dir().join(name);
+
+    // a new file, only readable by the user, so no other file is overwritten
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    // the last line ends with a line break, like in every text file
+    let content = if text.is_empty() { String::new() } else { format!("{}\n", text) };
+    if let Err(err) = options.open(&path).and_then(|mut file| file.write_all(content.as_bytes())) {
+        eprintln!("Can't create file: {} (error: {})", path.display(), err);
+        process::exit(1)
+    }
 
+    // the editor can have arguments, e.g. "code --wait"
+    let mut words = editor.split_whitespace();
+    let status = process::Command::new(words.next().unwrap_or("vi")).args(words).arg(&path).status();
+    let edited = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => {
+            eprintln!("The editor {} failed ({}), nothing is changed.", editor, status);
+            process::exit(1)
+        }
+        Err(err) => {
+            eprintln!("Can't start the editor {} (error: {})", editor, err);
+            process::exit(1)
+        }
+    }
+    match edited {
+        Ok(edited) => edited.trim_end().to_string(),
+        Err(err) => {
+            eprintln!("Can't read file: {} (error: {})", path.display(), err);
+            process::exit(1)
         }
-
     }
 
-    /// Restore the backup from the last resetted database
-    ///
-    /// **Command:**
-    ///
-    /// ```todo restore```
-    pub fn restore(&mut self) {
+}
 
-        print!("Restoring last database backup...");
+/// For internal use.
+///
+/// Read a priority, a letter from ```A``` (the highest) to ```Z``` or the names ```high```,
+/// ```medium``` and ```low``` (```A```, ```B``` and ```C```). ```none``` or ```-``` is no priority.
+pub(crate) fn parse_priority(s: &str) -> Result<Option<char>, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "high" | "h" => Ok(Some('A')),
+        "medium" | "med" | "m" => Ok(Some('B')),
+        "low" | "l" => Ok(Some('C')),
+        "none" | "-" => Ok(None),
+        p if p.len() == 1 && p.as_bytes()[0].is_ascii_lowercase() => Ok(Some(p.as_bytes()[0].to_ascii_uppercase() as char)),
+        _ => Err(format!("invalid priority '{}', use A to Z, high, medium, low or none", s)),
+    }
+}
 
-        let app_dir = get_os_data_dir();
-        let old_db = Path::new(&app_dir).join(APP_SUBDIR).join(APP_DATABASE);
-        let bak_db = Path::new(&app_dir).join(APP_SUBDIR).join(APP_BACKUP);
+/// For internal use.
+///
+/// Read a priority of the command line, the program stops with an invalid priority.
+fn parse_priority_or_exit(s: &str) -> Option<char> {
+    parse_priority(s).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1)
+    })
+}
 
-        let _cp = match copy(&bak_db, &old_db) {
-            Err(err) => {
-                eprintln!("Can't copy database: {} (error: {})", old_db.display(), err);
-                process::exit(1)
-            }
-            Ok(cp) => cp,
-        };
+/// For internal use.
+///
+/// Check, if the name is a command of todo, the case doesn't matter.
+pub(crate) fn is_command(name: &str) -> bool {
+    COMMANDS.contains(&name.to_ascii_lowercase().as_str())
+}
 
-        println!("done.");
+/// For internal use.
+///
+/// A tag is a word, which starts with ```+``` (a project) or ```@``` (a context) and a letter,
+/// e.g. ```+49``` or ```@5pm``` are text.
+pub(crate) fn is_tag(word: &str) -> bool {
+    (word.starts_with('+') || word.starts_with('@'))
+        && word[1..].starts_with(char::is_alphabetic)
+        && !word.contains(char::is_whitespace)
+}
 
+/// For internal use.
+///
+/// Split the tags out of a task, the result is the task without the tags and the tags, every
+/// tag only once.
+pub(crate) fn split_tags(task: &str) -> (String, Vec<String>) {
+    let mut words = Vec::new();
+    let mut tags: Vec<String> = Vec::new();
+    for word in task.split_whitespace() {
+        if !is_tag(word) {
+            words.push(word);
+        } else if !tags.iter().any(|t| t == word) {
+            tags.push(word.to_string());
+        }
     }
+    if tags.is_empty() {
+        return (task.trim().to_string(), tags);
+    }
+    (words.join(" "), tags)
+}
 
-    /// For internal use.
-    ///
-    /// The function do what the name says, looks for the highest ID in the database.
-    fn get_highest_id(&self) -> u32 {
-        let mut id: u32 = 0;
-        for ds in &self.v {
-            if ds.index > id { id = ds.index; }
+/// For internal use.
+///
+/// Read the value of ```--tag```, a tag without ```+``` or ```@``` is a project.
+fn tag_or_exit(s: &str) -> String {
+    let tag = if s.starts_with('+') || s.starts_with('@') { s.to_string() } else { format!("+{}", s) };
+    if !is_tag(&tag) {
+        eprintln!("Invalid tag: {}", s);
+        process::exit(1)
+    }
+    tag
+}
+
+/// For internal use.
+///
+/// Read a due date from the first words of the arguments, the result is the date and the number
+/// of words, e.g. ```in 3 days``` are three words. The program stops with an invalid date.
+fn take_due_or_exit(argv: &[String]) -> (u64, usize) {
+    for words in (1..=argv.len().min(3)).rev() {
+        if let Some(due) = parse_due(&argv[..words].join(" "), time_now()) {
+            return (due, words);
         }
-        id
     }
+    eprintln!("Invalid date: {}, use e.g. 2024-05-10, tomorrow, friday, next friday, in 3 days or eow",
+        argv.first().map(|a| a.as_str()).unwrap_or_default());
+    process::exit(1)
+}
 
+/// For internal use.
+///
+/// Read the rule of a recurring task, the program stops with an invalid rule.
+fn parse_recur_or_exit(s: &str) -> String {
+    parse_recur(s).unwrap_or_else(|| {
+        eprintln!("Invalid recurrence: {}, use e.g. daily, 2w, 1m, weekdays, friday or +3d (after done)", s);
+        process::exit(1)
+    })
 }
 
 /// For internal use.
 ///
-/// Very easy function to get the user dir depended on the operating system
-/// you work with this program on the command line, so the environment
-/// variables will be set.
-fn get_os_data_dir() -> String {
-    #[cfg(target_os="windows")]
-    let d = env::var("LOCALAPPDATA").unwrap_or_else(|err| {
-        eprintln!("could not find %LOCALAPPDATA%: {}", err);
-        String::from(".")
-    });
-    #[cfg(target_os="linux")]
-    let d = env::var("HOME").unwrap_or_else(|err| {
-        eprintln!("could not find %HOME: {}", err);
-        String::from(".")
-    });
-    d
+/// The rule of a recurring task as text, e.g. ```recurs every 1w``` or ```recurs 3d after done```.
+fn recur_string(rule: &str) -> String {
+    if let Some((rule, day)) = rule.split_once('@') {
+        return format!("recurs every {} on day {}", rule, day);
+    }
+    match rule.strip_prefix('+') {
+        Some(after) => format!("recurs {} after done", after),
+        None if rule == "weekdays" => String::from("recurs on weekdays"),
+        None => format!("recurs every {}", rule),
+    }
+}
+
+/// For internal use.
+///
+/// The colour of a priority, the three highest have their own colour.
+fn priority_color(p: char) -> &'static str {
+    match p {
+        'A' => "\x1B[91m",
+        'B' => "\x1B[93m",
+        'C' => "\x1B[96m",
+        _ => "\x1B[39m",
+    }
+}
+
+/// For internal use.
+///
+/// Should the output be colourful, see the key ```color``` of the configuration.
+fn use_color() -> bool {
+    config().color()
+}
+
+/// For internal use.
+///
+/// A date and time in the format of the key ```date_format``` of the configuration.
+fn date_time(secs: u64) -> String {
+    format_time(secs, &config().date_format())
+}
+
+/// For internal use.
+///
+/// A due date in the date part of the key ```date_format``` of the configuration.
+fn due_date(secs: u64) -> String {
+    format_time(secs, &date_format(&config().date_format()))
+}
+
+/// For internal use.
+///
+/// A hash of the file content, to detect if a file was changed.
+fn hash_bytes(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// For internal use.
+///
+/// A new random UUID (version 4), the permanent ID of a task. The random numbers are the hashes
+/// of the time with the random keys of the standard library, so no crate is needed.
+pub(crate) fn new_uid() -> String {
+    let mut bytes = [0u8; 16];
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_nanos()).unwrap_or_default();
+    for (i, chunk) in bytes.chunks_mut(8).enumerate() {
+        chunk.copy_from_slice(&RandomState::new().hash_one((nanos, process::id(), i)).to_le_bytes());
+    }
+    bytes[6] = bytes[6] & 0x0f | 0x40;
+    bytes[8] = bytes[8] & 0x3f | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// For internal use.
+///
+/// Check, if the text is a permanent ID like ```6f1c2a9e-3b4d-4e5f-8a7b-9c0d1e2f3a4b```.
+pub(crate) fn is_uid(s: &str) -> bool {
+    s.len() == 36 && s.char_indices().all(|(i, c)| if [8, 13, 18, 23].contains(&i) { c == '-' } else { c.is_ascii_hexdigit() && !c.is_ascii_uppercase() })
+}
+
+/// For internal use.
+///
+/// The current time in seconds since the UNIX epoch.
+pub(crate) fn time_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_secs()).unwrap_or_default()
 }
 
 /// For internal use.
@@ -549,50 +3081,638 @@ fn get_os_data_dir() -> String {
 /// for this duration calculation it is accurate enough.
 fn time_diff(secs: u64) -> u64 {
     let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-    let diff: u64 = now.as_secs() - secs;
+    // a time in the future, e.g. of another computer with a wrong clock, is no time ago
+    let diff: u64 = now.as_secs().saturating_sub(secs);
     diff
 }
 
 /// For internal use.
 ///
 /// Get the ```String``` from the ```time_diff()``` function.
-fn since_string(secs: u64) -> String {
-    let diff: u64 = time_diff(secs);
-    let s: String;
+pub(crate) fn since_string(secs: u64) -> String {
+    format!("(since {})", duration_string(time_diff(secs)))
+}
+
+/// For internal use.
+///
+/// The time of completion and how long it took, e.g. ```(done 3 hour(s) ago, after 2 day(s))```.
+pub(crate) fn done_string(start: u64, completed: u64) -> String {
+    let ago = duration_string(time_now().saturating_sub(completed));
+    format!("(done {} ago, after {})", ago, duration_string(completed.saturating_sub(start)))
+}
+
+/// For internal use.
+///
+/// A duration in the largest unit, e.g. ```3 hour(s)```.
+fn duration_string(diff: u64) -> String {
     if diff < 60 {
-        s = format!("(since {} second(s))", diff);
-    } else if diff >= 60 && diff < 3_600 {
-        s = format!("(since {} minute(s))", diff / 60);
-    } else if diff >= 3_600 && diff < 86_400 {
-        s = format!("(since {} hour(s))", diff / 3_600);
+        format!("{} second(s)", diff)
+    } else if (60..3_600).contains(&diff) {
+        format!("{} minute(s)", diff / 60)
+    } else if (3_600..86_400).contains(&diff) {
+        format!("{} hour(s)", diff / 3_600)
     } else {
-        s = format!("(since {} day(s))", diff / 86_400);
+        format!("{} day(s)", diff / 86_400)
+    }
+}
+
+/// For internal use.
+///
+/// Set the times of an imported task, which are in the future, to now, e.g. a ```DTSTART``` of
+/// an iCalendar file is the start of a task in a calendar. The result is true, if a time was changed.
+fn clamp_to_now(ds: &mut TodoItem) -> bool {
+    let now = time_now();
+    let mut changed = ds.start > now || ds.completed.is_some_and(|c| c > now);
+    ds.start = ds.start.min(now);
+    ds.completed = ds.completed.map(|c| c.min(now));
+    for (start, end) in &mut ds.sessions {
+        changed = changed || *start > now || end.is_some_and(|e| e > now);
+        *start = (*start).min(now);
+        *end = end.map(|e| e.min(now));
+    }
+    changed
+}
+
+/// For internal use.
+///
+/// A tracked time as hours and minutes, e.g. ```2:05```.
+fn hours_string(secs: u64) -> String {
+    format!("{}:{:02}", secs / 3_600, secs % 3_600 / 60)
+}
+
+/// For internal use.
+///
+/// Read a day of the time report, an ISO date, ```today``` or ```yesterday```, the program stops
+/// with an invalid date.
+fn report_date_or_exit(s: &str) -> u64 {
+    let today = time_now() / 86_400 * 86_400;
+    match s.to_ascii_lowercase().as_str() {
+        "today" => today,
+        "yesterday" => today - 86_400,
+        date => parse_date(date).unwrap_or_else(|| {
+            eprintln!("Invalid date: {}, use YYYY-MM-DD, today or yesterday", s);
+            process::exit(1)
+        }),
+    }
+}
+
+/// For internal use.
+///
+/// The due date relative to today, e.g. ```due in 2 days``` or ```overdue by 1 day```.
+pub(crate) fn due_string(due: u64) -> String {
+    let days = (due / 86_400) as i64 - (time_now() / 86_400) as i64;
+    match days {
+        0 => String::from("due today"),
+        1 => String::from("due tomorrow"),
+        -1 => String::from("overdue by 1 day"),
+        d if d < 0 => format!("overdue by {} days", -d),
+        d => format!("due in {} days", d),
     }
-    s
 }
 
 /// Print out some help Text.
 pub fn help(name: &str) {
     println!("\nUsage:");
     println!("------");
-    println!("{} [<OPTION>] or [COMMAND] [<ARGUMENTS>]\n", name);
+    println!("{} [<OPTION>] or [--db PATH|--data-dir DIR|--list NAME|--global] [COMMAND] [<ARGUMENTS>]\n", name);
     println!("Options:");
     println!("--------");
     println!("-h, --help         show this help");
     println!("-v, --version      show the program version and exit");
-    println!("");
+    println!("--db PATH          use the database file PATH for this command");
+    println!("--data-dir DIR     use the database in the directory DIR for this command");
+    println!("--list NAME        use the list NAME for this command");
+    println!("-g, --global       use the global list, not the {} file of a project", PROJECT_FILE);
+    println!();
     println!("Commands:");
     println!("---------");
-    println!("{}, {}, {}, {}, {}, {}, {}", CMD_ADD, CMD_DONE, CMD_EDIT, CMD_LIST, CMD_REMOVE, CMD_RESET, CMD_RESTORE);
-    println!("");
+    let commands = [CMD_ADD, CMD_BACKUPS, CMD_BLOCK, CMD_CHECK, CMD_CONFIG, CMD_DONE, CMD_DUE, CMD_EDIT, CMD_EXPORT, CMD_IMPORT,
+        CMD_INIT, CMD_LIST, CMD_LISTS, CMD_MOVE, CMD_NOTE, CMD_PRIO, CMD_READY, CMD_RECUR, CMD_REDO, CMD_REMOVE, CMD_RESET,
+        CMD_RESTORE, CMD_SHOW, CMD_START, CMD_STOP, CMD_TAGS, CMD_TIME, CMD_UNBLOCK, CMD_UNDO, CMD_UNDONE];
+    println!("{}", commands.join(", "));
+    println!();
     println!("Command usage:");
     println!("--------------");
-    println!("{}\t[TASK]        \tadd the TASK to the todo list", CMD_ADD);
-    println!("{}\t[INDEX]       \tmark the task with INDEX as done", CMD_DONE);
+    println!("{}\t[-p PRIORITY] [-d DATE] [-t TAG] [--parent INDEX] [--every RULE] [TASK]\n\t\t\tadd the TASK to the todo list, +project and @context are tags", CMD_ADD);
+    println!("{}\t              \tshow the snapshots of the database", CMD_BACKUPS);
+    println!("{}\t[INDEX] --by [INDEX,...]\n\t\t\tthe task with INDEX can't start before the other tasks are done", CMD_BLOCK);
+    println!("{}\t[--repair]    \tcheck the database and repair it (alias: fsck)", CMD_CHECK);
+    println!("{}\t[list|get KEY|set KEY VALUE|unset KEY]\n\t\t\tshow or change the configuration file\n\t\t\t$XDG_CONFIG_HOME/todo/todo.conf", CMD_CONFIG);
+    println!("{}\t[INDEX] [--children]\n\t\t\tmark the task with INDEX as done, with the open subtasks", CMD_DONE);
+    println!("{}\t[INDEX DATE] [--days N]\n\t\t\tset the due date (e.g. tomorrow, next friday, in 3 days, eow, none)\n\t\t\tor show the upcoming deadlines", CMD_DUE);
     println!("{}\t[INDEX] [TASK]\treplace the task with INDEX with TASK", CMD_EDIT);
-    println!("{}                \tprint out all tasks", CMD_LIST);
-    println!("{}\t[TASK]        \tremove task with INDEX fro list", CMD_REMOVE);
+    println!("{}\t[--format FORMAT] [--output FILE]\n\t\t\texport all tasks, FORMAT: json, todotxt, markdown or ical", CMD_EXPORT);
+    println!("{}\t[--merge|--replace] [--format FORMAT] [FILE]\n\t\t\timport tasks from FILE, duplicates are skipped", CMD_IMPORT);
+    println!("{}\t              \tcreate the task list {} of a project in this directory", CMD_INIT, PROJECT_FILE);
+    println!("{}\t[--all|--open|--done] [--all-lists] [--sort id|prio|due] [--priority PRIORITY]\n\t\t\t[--uid] [+TAG] [-+TAG]\n\t\t\tprint out the tasks, all without option, only with or without a TAG", CMD_LIST);
+    println!("{}\t[create NAME|rename OLD NEW|delete NAME]\n\t\t\tshow, create, rename or delete the named lists", CMD_LISTS);
+    println!("{}\t[INDEX] [LIST] \tmove the task with INDEX and its subtasks into the list LIST", CMD_MOVE);
+    println!("{}\t[INDEX] [TEXT|--clear]\n\t\t\tedit the notes of the task in $VISUAL or $EDITOR, add a line or remove them", CMD_NOTE);
+    println!("{}\t[INDEX] [PRIORITY]\tset the priority A-Z, high, medium, low or none", CMD_PRIO);
+    println!("{}\t              \tshow the open tasks, which are not blocked", CMD_READY);
+    println!("{}\t[INDEX RULE|INDEX stop]\n\t\t\tshow the recurring tasks, set the RULE (e.g. daily, 2w, 1m, weekdays,\n\t\t\tfriday or +3d after done) or stop the recurrence", CMD_RECUR);
+    println!("{}\t[N]           \tredo the last N undone changes", CMD_REDO);
+    println!("{}\t[INDEX] [--keep-children]\n\t\t\tremove task with INDEX and its subtasks fro list", CMD_REMOVE);
     println!("{}\t              \treset (delete) the whole database", CMD_RESET);
-    println!("{}\t              \trestore a backup from the last deleted database", CMD_RESTORE);
-    println!("");
+    println!("{}\t[NUMBER|FILE] \trestore a snapshot, the newest without NUMBER", CMD_RESTORE);
+    println!("{}\t[INDEX]       \tshow all details of the task with its notes", CMD_SHOW);
+    println!("{}\t[INDEX]       \tstart the time tracking of the task with INDEX", CMD_START);
+    println!("{}\t              \tstop the time tracking of the running task", CMD_STOP);
+    println!("{}\t              \tshow all tags with the number of tasks", CMD_TAGS);
+    println!("{}\t[--by task|day|tag] [--from DATE] [--to DATE] [--today|--week|--month]\n\t\t\t[--csv] [--output FILE]\n\t\t\tshow the tracked time, or export it as CSV", CMD_TIME);
+    println!("{}\t[INDEX] [--by INDEX,...]\n\t\t\tremove the dependencies of the task with INDEX", CMD_UNBLOCK);
+    println!("{}\t[N] [--list]  \tundo the last N changes or show the recorded changes", CMD_UNDO);
+    println!("{}\t[INDEX]       \treopen the task with INDEX, which was marked as done", CMD_UNDONE);
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::sync::Once;
+
+    /// An empty database in its own directory, the tests never ask and never use the real
+    /// directories of the user.
+    fn test_db(name: &str) -> TodoDatabase {
+        static ENV: Once = Once::new();
+        let base = env::temp_dir().join(format!("todo-test-{}", process::id()));
+        ENV.call_once(|| {
+            env::set_var("XDG_DATA_HOME", base.join("data"));
+            env::set_var("XDG_CONFIG_HOME", base.join("config"));
+            env::set_var("TODO_DATA_DIR", base.join("data"));
+            env::set_var("TODO_STORAGE", "csv");
+            env::set_var("TODO_CONFIRM", "false");
+            env::set_var("TODO_COLOR", "never");
+            for key in ["TODO_DB", "TODO_LIST", "TODO_GLOBAL", "TODO_BACKUP_KEEP"] {
+                env::remove_var(key);
+            }
+        });
+        let dir = base.join("tests").join(name);
+        let _ = fs::remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        TodoDatabase::new(open_storage(&dir.join("todo.data"), "csv").unwrap())
+    }
+
+    /// Load the database again from its file.
+    fn reload(db: &TodoDatabase) -> TodoDatabase {
+        TodoDatabase::load_from(db.storage.with_path(db.storage.path()))
+    }
+
+    fn args(argv: &[&str]) -> Vec<String> {
+        argv.iter().map(|a| a.to_string()).collect()
+    }
+
+    fn tasks(db: &TodoDatabase) -> Vec<(u32, &str)> {
+        db.v.iter().map(|ds| (ds.index, ds.task.as_str())).collect()
+    }
+
+    #[test]
+    fn check_repairs_damaged_lines() {
+        let db = test_db("check");
+        let path = db.storage.path().to_path_buf();
+        let text = format!("#!todo-format;{}\n1;first;1714564800;false;;;;;;;;;{};\n2;damaged;1714564800;maybe;;;;;;;;;{};\n1;duplicate;1714564800;false;;;;;;;;;{};\n",
+            FORMAT_VERSION, new_uid(), new_uid(), new_uid());
+        fs::write(&path, text).unwrap();
+        let mut db = reload(&db);
+        assert_eq!((db.damaged.len(), db.bad_index.len()), (1, 1));
+        db.check(&args(&["--repair"]));
+        assert!(db.damaged.is_empty() && db.bad_index.is_empty() && db.changed);
+        assert_eq!(tasks(&db), vec![(1, "first"), (2, "duplicate")]);
+        let rejected = fs::read_to_string(side_file(&path, EXT_REJECTED)).unwrap();
+        assert!(rejected.contains("2;damaged;1714564800;maybe") && rejected.contains("invalid state"));
+        db.save("check");
+        let db = reload(&db);
+        assert!(db.damaged.is_empty() && db.bad_index.is_empty());
+        assert_eq!(tasks(&db), vec![(1, "first"), (2, "duplicate")]);
+    }
+
+    #[test]
+    fn check_keeps_lines_without_rejected_file() {
+        let db = test_db("check-rejected");
+        let path = db.storage.path().to_path_buf();
+        let text = format!("#!todo-format;{}\n1;damaged;1714564800;maybe;;;;;;;;;{};\n", FORMAT_VERSION, new_uid());
+        fs::write(&path, text).unwrap();
+        let db = reload(&db);
+        // a directory can't be opened as file
+        let rejected = side_file(&path, EXT_REJECTED);
+        create_dir_all(&rejected).unwrap();
+        assert!(db.write_rejected(&rejected).is_err());
+        assert_eq!(db.damaged.len(), 1);
+    }
+
+    /// Write a file into the directory of the database.
+    fn write_file(db: &TodoDatabase, name: &str, text: &str) -> String {
+        let path = db.storage.path().with_file_name(name);
+        fs::write(&path, text).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn import_merges_duplicates() {
+        let mut db = test_db("import-merge");
+        db.add(&args(&["buy milk"]));
+        db.add(&args(&["call mom"]));
+        let file = write_file(&db, "tasks.md", "- [x] buy milk
+- [ ] call mom
+- [ ] new task
+- [ ] new task
+");
+        db.import(&args(&[&file]));
+        // a file without IDs is compared by the text, but never with itself
+        assert_eq!(tasks(&db), vec![(1, "buy milk"), (2, "call mom"), (3, "new task"), (4, "new task")]);
+        assert!(db.v[0].is_done && !db.v[1].is_done);
+        assert!(db.v[0].completed.is_some_and(|c| c + 60 > time_now()));
+        assert_ne!(db.v[2].uid, db.v[3].uid);
+
+        // a file with IDs is compared by the ID, a changed text is still the same task
+        let mut other = test_db("import-merge-other");
+        other.import(&args(&[&write_file(&db, "tasks.json", &export_json(&db.v))]));
+        assert_eq!(tasks(&other), tasks(&db));
+        other.v[1].task = String::from("call mom today");
+        other.v[1].is_done = true;
+        other.v[1].completed = Some(1_714_564_800);
+        let mut ds = TodoItem::from(5, String::from("buy milk"), 1_714_564_800, false);
+        ds.uid = new_uid();
+        other.v.push(ds);
+        db.import(&args(&[&write_file(&db, "tasks.json", &export_json(&other.v))]));
+        assert_eq!(db.v.len(), 5);
+        assert_eq!((db.v[1].task.as_str(), db.v[1].is_done, db.v[1].completed), ("call mom", true, Some(1_714_564_800)));
+        assert_eq!((db.v[4].index, db.v[4].task.as_str()), (5, "buy milk"));
+    }
+
+    #[test]
+    fn import_replaces_list() {
+        let mut db = test_db("import-replace");
+        db.add(&args(&["old task"]));
+        let file = write_file(&db, "tasks.txt", "(A) 2024-05-01 first +work
+x 2024-05-02 2024-05-01 second
+");
+        db.import(&args(&["--replace", &file]));
+        assert_eq!(tasks(&db), vec![(1, "first"), (2, "second")]);
+        assert_eq!(db.v[0].priority, Some('A'));
+        assert!(db.v[1].is_done);
+        assert!(db.v.iter().all(|ds| is_uid(&ds.uid)));
+        assert!(db.changed);
+    }
+
+    #[test]
+    fn backup_names() {
+        let order = |name: &str| backup_order(Path::new(name), "todo");
+        assert_eq!(order("todo-20240501-120000.data"), Some((String::from("20240501-120000"), 1)));
+        assert_eq!(order("todo-20240501-120000-10.data"), Some((String::from("20240501-120000"), 10)));
+        assert!(order("todo-20240501-120000-2.data") < order("todo-20240501-120000-10.data"));
+        assert_eq!(order("other-20240501-120000.data"), None);
+    }
+
+    #[test]
+    fn snapshots_keep_their_order() {
+        let mut db = test_db("snapshots");
+        for task in ["one", "two", "three", "four"] {
+            db.add(&args(&[task]));
+            db.save("add");
+            db = reload(&db);
+        }
+        // the first save has no file to keep
+        let backups = db.list_backups();
+        assert_eq!(backups.len(), 3);
+        let tasks: Vec<usize> = backups.iter().map(|p| db.load_backup(p).len()).collect();
+        assert_eq!(tasks, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut db = test_db("undo");
+        for task in ["one", "two"] {
+            db.add(&args(&[task]));
+            db.save(format!("add {}", task).as_str());
+            db = reload(&db);
+        }
+        db.undo(&[]);
+        assert_eq!(tasks(&db), vec![(1, "one")]);
+        db.save("undo");
+        let mut db = reload(&db);
+        db.redo(&[]);
+        assert_eq!(tasks(&db), vec![(1, "one"), (2, "two")]);
+        db.save("redo");
+        let mut db = reload(&db);
+        db.undo(&args(&["2"]));
+        assert!(db.v.is_empty());
+    }
+
+    #[test]
+    fn undo_with_todotxt() {
+        let db = test_db("undo-todotxt");
+        let path = db.storage.path().with_file_name("todo.txt");
+        let mut db = TodoDatabase::new(open_storage(&path, "todotxt").unwrap());
+        // todo.txt has no line breaks in a task, the journal has the saved state
+        db.add(&args(&["two\nlines"]));
+        db.save("add");
+        let mut db = reload(&db);
+        db.done(&args(&["1"]));
+        db.save("done 1");
+        let mut db = reload(&db);
+        db.undo(&[]);
+        assert!(!db.v[0].is_done);
+        assert_eq!(db.v[0].task, "two lines");
+    }
+
+    /// The number in the list of the task with the permanent ID.
+    fn index(db: &TodoDatabase, uid: &String) -> u32 {
+        db.v.iter().find(|ds| ds.uid == *uid).map(|ds| ds.index).unwrap_or_default()
+    }
+
+    /// The parent of a task, as number in the list.
+    fn parent(db: &TodoDatabase, item: u32) -> Option<u32> {
+        db.v.iter().find(|ds| ds.index == item).and_then(|ds| ds.parent.as_ref()).map(|p| index(db, p))
+    }
+
+    /// The tasks, which block a task, as numbers in the list.
+    fn depends(db: &TodoDatabase, item: u32) -> Vec<u32> {
+        db.v.iter().find(|ds| ds.index == item).map(|ds| ds.depends.iter().map(|d| index(db, d)).collect()).unwrap_or_default()
+    }
+
+    #[test]
+    fn move_task_with_subtasks() {
+        let mut db = test_db("move");
+        let target = list_path("movetest");
+        create_dir_all(target.parent().unwrap()).unwrap();
+        let mut other = TodoDatabase::new(open_storage(&target, "csv").unwrap());
+        other.add(&args(&["already there"]));
+        other.save("add");
+        db.add(&args(&["first"]));
+        db.add(&args(&["parent"]));
+        db.add(&args(&["--parent", "2", "child"]));
+        db.add(&args(&["blocked"]));
+        db.block(&args(&["4", "--by", "3"]));
+        db.move_task(&args(&["2", "movetest"]));
+        // the moved tasks are gone, a dependency on them is lost
+        assert_eq!(tasks(&db), vec![(1, "first"), (2, "blocked")]);
+        assert!(depends(&db, 2).is_empty());
+        let other = TodoDatabase::load_from(list_storage(&target));
+        assert_eq!(tasks(&other), vec![(1, "already there"), (2, "parent"), (3, "child")]);
+        assert_eq!((parent(&other, 2), parent(&other, 3)), (None, Some(2)));
+        fs::remove_file(&target).unwrap();
+    }
+
+    #[test]
+    fn migrate_old_database() {
+        let db = test_db("migrate");
+        let path = db.storage.path().to_path_buf();
+        fs::write(&path, "1;(B) old task +home;1714564800;false\n").unwrap();
+        let mut db = reload(&db);
+        assert_eq!(db.version, 1);
+        db.migrate();
+        assert_eq!(db.version, FORMAT_VERSION);
+        assert!(side_file(&path, "v1.bak").exists());
+        let db = reload(&db);
+        assert_eq!(db.version, FORMAT_VERSION);
+        assert_eq!(tasks(&db), vec![(1, "old task")]);
+        assert_eq!((db.v[0].priority, &db.v[0].tags), (Some('B'), &vec![String::from("+home")]));
+    }
+
+    #[test]
+    fn priorities() {
+        assert_eq!(parse_priority("high"), Ok(Some('A')));
+        assert_eq!(parse_priority("c"), Ok(Some('C')));
+        assert_eq!(parse_priority("none"), Ok(None));
+        assert!(parse_priority("AA").is_err());
+    }
+
+    #[test]
+    fn prio_command() {
+        let mut db = test_db("prio");
+        db.add(&args(&["-p", "b", "task"]));
+        assert_eq!(db.v[0].priority, Some('B'));
+        db.prio(&args(&["1", "high"]));
+        assert_eq!(db.v[0].priority, Some('A'));
+        db.prio(&args(&["1", "none"]));
+        assert_eq!(db.v[0].priority, None);
+    }
+
+    #[test]
+    fn due_command() {
+        let mut db = test_db("due");
+        db.add(&args(&["--due", "2024-06-10", "task"]));
+        assert_eq!(db.v[0].due, parse_date("2024-06-10"));
+        db.due(&args(&["1", "in", "2", "days"]));
+        assert_eq!(db.v[0].due, Some((time_now() / 86_400 + 2) * 86_400));
+        db.due(&args(&["1", "none"]));
+        assert_eq!(db.v[0].due, None);
+    }
+
+    #[test]
+    fn tags() {
+        assert_eq!(split_tags("buy  milk +shop @home +shop"), (String::from("buy milk"), vec![String::from("+shop"), String::from("@home")]));
+        assert_eq!(split_tags(" no tags + @ "), (String::from("no tags + @"), Vec::new()));
+        assert_eq!(split_tags("call +49 30 123 @5pm +ä"), (String::from("call +49 30 123 @5pm"), vec![String::from("+ä")]));
+        assert!(is_tag("+p") && is_tag("@c") && !is_tag("+") && !is_tag("p") && !is_tag("+1") && !is_tag("@-"));
+    }
+
+    #[test]
+    fn done_and_undone() {
+        let mut db = test_db("undone");
+        db.add(&args(&["task"]));
+        db.done(&args(&["1"]));
+        assert!(db.v[0].is_done);
+        let completed = db.v[0].completed.unwrap();
+        assert!(completed >= db.v[0].start);
+        db.done(&args(&["1"]));
+        assert_eq!(db.v[0].completed, Some(completed));
+        db.undone(&args(&["1"]));
+        assert!(!db.v[0].is_done);
+        assert_eq!(db.v[0].completed, None);
+        db.save("undone");
+        let db = reload(&db);
+        assert!(!db.v[0].is_done && db.v[0].completed.is_none());
+    }
+
+    #[test]
+    fn done_and_remove_with_subtasks() {
+        let mut db = test_db("subtasks");
+        db.add(&args(&["release"]));
+        db.add(&args(&["--parent", "1", "build"]));
+        db.add(&args(&["--parent", "2", "test"]));
+        db.add(&args(&["docs"]));
+        db.add(&args(&["--parent", "4", "readme"]));
+        assert_eq!(db.descendants(1), vec![2, 3]);
+        db.done(&args(&["1", "--children"]));
+        assert!(db.v[..3].iter().all(|ds| ds.is_done && ds.completed.is_some()));
+        assert!(!db.v[3].is_done && !db.v[4].is_done);
+
+        // the subtasks are removed too, the parent of the remaining subtask follows the renumbering
+        db.remove(&args(&["1"]));
+        assert_eq!(tasks(&db), vec![(1, "docs"), (2, "readme")]);
+        assert_eq!(parent(&db, 2), Some(1));
+
+        db.add(&args(&["--parent", "2", "intro"]));
+        db.add(&args(&["--parent", "3", "example"]));
+        db.remove(&args(&["2", "--keep-children"]));
+        assert_eq!(tasks(&db), vec![(1, "docs"), (2, "intro"), (3, "example")]);
+        assert_eq!(parent(&db, 2), Some(1));
+        assert_eq!(parent(&db, 3), Some(2));
+    }
+
+    #[test]
+    fn block_unblock_and_remove() {
+        let mut db = test_db("block");
+        for task in ["design", "spec", "build", "ship"] {
+            db.add(&args(&[task]));
+        }
+        db.block(&args(&["3", "--by", "1,2"]));
+        db.block(&args(&["4", "--by", "3"]));
+        assert_eq!(depends(&db, 3), vec![1, 2]);
+        assert!(db.depends_on(4, 1) && !db.depends_on(1, 4));
+        assert_eq!(db.blockers(&db.v[3]), vec![3]);
+        db.done(&args(&["3"]));
+        assert!(db.blockers(&db.v[3]).is_empty());
+        db.undone(&args(&["3"]));
+
+        // the dependencies follow the renumbering, a removed blocker is gone
+        db.remove(&args(&["1"]));
+        assert_eq!(tasks(&db), vec![(1, "spec"), (2, "build"), (3, "ship")]);
+        assert_eq!(depends(&db, 2), vec![1]);
+        assert_eq!(depends(&db, 3), vec![2]);
+
+        db.unblock(&args(&["3", "--by", "1"]));
+        assert_eq!(depends(&db, 3), vec![2]);
+        db.unblock(&args(&["2"]));
+        assert!(depends(&db, 2).is_empty());
+    }
+
+    #[test]
+    fn recurring_task_keeps_its_day() {
+        let mut db = test_db("recur");
+        db.add(&args(&["--due", "2099-01-31", "--every", "monthly", "pay rent"]));
+        assert_eq!(db.v[0].recur.as_deref(), Some("1m@31"));
+        for (i, date) in ["2099-02-28", "2099-03-31", "2099-04-30"].iter().enumerate() {
+            db.done(&args(&[(i + 1).to_string().as_str()]));
+            assert_eq!(db.v[i + 1].due, parse_date(date));
+            assert_eq!(db.v[i + 1].recur.as_deref(), Some("1m@31"));
+        }
+        // a new due date is the day of the series
+        db.due(&args(&["4", "2099-05-15"]));
+        assert_eq!(db.v[3].recur.as_deref(), Some("1m"));
+        db.recur(&args(&["4", "1y"]));
+        assert_eq!(db.v[3].recur.as_deref(), Some("1y"));
+    }
+
+    #[test]
+    fn note_command() {
+        let mut db = test_db("note");
+        db.add(&args(&["task"]));
+        db.note(&args(&["1", "first", "line"]));
+        db.note(&args(&["1", "second line"]));
+        assert_eq!(db.v[0].notes, "first line\nsecond line");
+        db.save("note");
+        let mut db = reload(&db);
+        assert_eq!(db.v[0].notes, "first line\nsecond line");
+        db.note(&args(&["1", "--clear"]));
+        assert!(db.v[0].notes.is_empty());
+    }
+
+    #[test]
+    fn uids() {
+        let a = new_uid();
+        let b = new_uid();
+        assert!(is_uid(&a) && is_uid(&b));
+        assert_ne!(a, b);
+        assert_eq!(&a[14..15], "4");
+        assert!("89ab".contains(&a[19..20]));
+        assert!(!is_uid(&a.to_ascii_uppercase().replace(char::is_numeric, "A")));
+        assert!(!is_uid(&a[1..]));
+    }
+
+    #[test]
+    fn find_index_by_number_or_uid() {
+        let mut db = test_db("find-index");
+        db.add(&args(&["first"]));
+        db.add(&args(&["second"]));
+        db.v[0].uid = String::from("1234abcd-0000-4000-8000-000000000000");
+        db.v[1].uid = String::from("1234abce-0000-4000-8000-000000000000");
+        assert_eq!(db.find_index("2"), Ok(2));
+        assert!(db.find_index("3").is_err());
+        // a number is never the start of a permanent ID, a # or a letter is needed
+        assert!(db.find_index("1234").is_err());
+        assert_eq!(db.find_index("#1234abcd"), Ok(1));
+        assert_eq!(db.find_index("1234ABCE"), Ok(2));
+        assert_eq!(db.find_index("#1234"), Err(String::from("The id #1234 is ambiguous, it matches 2 tasks.")));
+        assert!(db.find_index("#123").is_err());
+        assert_eq!(db.short_uid(&db.v[0].uid), "1234abcd");
+    }
+
+    #[test]
+    fn links_keep_their_task() {
+        let mut db = test_db("links");
+        for task in ["first", "release", "build", "test"] {
+            db.add(&args(&[task]));
+        }
+        db.add(&args(&["--parent", "2", "docs"]));
+        db.block(&args(&["4", "--by", "3"]));
+        let (release, build) = (db.v[1].uid.clone(), db.v[2].uid.clone());
+        assert_eq!(db.v[4].parent.as_ref(), Some(&release));
+        assert_eq!(db.v[3].depends, vec![build.clone()]);
+
+        // the numbers change, the links are the same tasks
+        db.remove(&args(&["1"]));
+        assert_eq!(tasks(&db), vec![(1, "release"), (2, "build"), (3, "test"), (4, "docs")]);
+        assert_eq!((db.v[3].parent.as_ref(), &db.v[2].depends), (Some(&release), &vec![build]));
+        assert_eq!((parent(&db, 4), depends(&db, 3)), (Some(1), vec![2]));
+        db.save("links");
+        let db = reload(&db);
+        assert_eq!((parent(&db, 4), depends(&db, 3)), (Some(1), vec![2]));
+    }
+
+    #[test]
+    fn import_links() {
+        let mut db = test_db("import-links");
+        db.add(&args(&["release"]));
+        // Markdown links a subtask by the number of its parent in the file
+        db.import(&args(&[&write_file(&db, "tasks.md", "- [ ] plan\n  - [ ] step one\n  - [ ] step two\n- [ ] other\n")]));
+        assert_eq!(tasks(&db), vec![(1, "release"), (2, "plan"), (3, "step one"), (4, "step two"), (5, "other")]);
+        assert_eq!((parent(&db, 3), parent(&db, 4), parent(&db, 5)), (Some(2), Some(2), None));
+
+        // a subtask of a duplicate is linked to the task in the list
+        let mut other = test_db("import-links-other");
+        other.import(&args(&[&write_file(&db, "tasks.json", &export_json(&db.v))]));
+        other.add(&args(&["--parent", "1", "announce"]));
+        db.import(&args(&[&write_file(&db, "tasks.json", &export_json(&other.v))]));
+        assert_eq!(db.v.len(), 6);
+        assert_eq!(parent(&db, 6), Some(1));
+
+        // an older todo.txt links the tasks by the line
+        db.import(&args(&[&write_file(&db, "old.txt", "2024-05-01 blocked dep:2\n2024-05-01 blocker\n")]));
+        assert_eq!(tasks(&db)[6..], [(7, "blocked"), (8, "blocker")]);
+        assert_eq!(depends(&db, 7), vec![8]);
+    }
+
+    #[test]
+    fn start_stop_and_time() {
+        let mut db = test_db("time");
+        db.add(&args(&["write report +work"]));
+        db.add(&args(&["review"]));
+        db.start(&args(&["1"]));
+        assert_eq!(db.running(), Some(1));
+        db.v[0].sessions[0].0 -= 3_600;
+
+        // only one task is tracked, starting another one stops it
+        db.start(&args(&["2"]));
+        assert_eq!(db.running(), Some(2));
+        let (start, end) = db.v[0].sessions[0];
+        assert!(end.is_some_and(|end| end - start >= 3_600));
+        db.stop();
+        assert_eq!(db.running(), None);
+        // a session without time is removed
+        assert!(db.v[1].sessions.is_empty());
+
+        let file = write_file(&db, "time.csv", "");
+        db.time(&args(&["--by", "tag", "--output", &file]));
+        assert_eq!(fs::read_to_string(&file).unwrap(), "tag,hours\r\n+work,1.00\r\n");
+
+        // a finished task isn't tracked anymore
+        db.start(&args(&["1"]));
+        db.done(&args(&["1"]));
+        assert_eq!(db.running(), None);
+    }
+
 }
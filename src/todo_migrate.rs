@@ -0,0 +1,379 @@
+//! The migrations of the database format.
+//!
+//! Every file of the database stores its format version (```#!todo-format;2``` in the CSV file,
+//! ```"format": 2``` in the JSON file and ```PRAGMA user_version``` in SQLite). When a new field
+//! is added to ```TodoItem```, ```FORMAT_VERSION``` is increased and a ```Migration``` is added to
+//! ```MIGRATIONS```, it converts a task of the previous version for every backend. A change of
+//! the content, which is the same for all backends, is done on the ```TodoItem``` after it's read.
+//!
+//! Older files are migrated while they are read, so old backups and journal entries can still be
+//! restored. The database itself is saved in the new format once, the old file is kept as
+//! ```todo.data.v1.bak```. A file of a newer version is never loaded, its new fields would be lost.
+
+use crate::todo_export::split_priority;
+use crate::todo_json::JsonValue;
+use crate::todo_lib::{new_uid, split_tags, TodoItem};
+use crate::todo_storage::FORMAT_VERSION;
+
+/// The changes of the format from ```version - 1``` to ```version```.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    /// Convert the fields of a CSV line, e.g. append the default of a new field.
+    pub fields: fn(&mut Vec<String>),
+    /// Convert a task object of the JSON file.
+    pub json: fn(&mut Vec<(String, JsonValue)>),
+    /// The SQL statements for the table ```tasks```.
+    #[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+    pub sql: &'static str,
+    /// Convert the task after it's read in the current format.
+    pub item: fn(&mut TodoItem),
+    /// Convert the tasks of a file after all are read, e.g. the links between the tasks.
+    pub list: fn(&mut [&mut TodoItem]),
+}
+
+/// All migrations, sorted by the version.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 2,
+        description: "the fields of the CSV file are escaped, the format header is added",
+        fields: |_| {},
+        json: |_| {},
+        sql: "",
+        item: |_| {},
+        list: |_| {},
+    },
+    Migration {
+        version: 3,
+        description: "the priority is a field, a priority like (A) at the start of a task is moved into it",
+        fields: |f| {
+            if f.len() == 4 {
+                let p = take_priority(&mut f[1]);
+                f.push(p.map(String::from).unwrap_or_default());
+            }
+        },
+        json: |members| {
+            let mut p = None;
+            if let Some((_, JsonValue::String(task))) = members.iter_mut().find(|(k, _)| k == "task") {
+                p = take_priority(task);
+            }
+            members.push((String::from("priority"), p.map(|p| JsonValue::String(p.to_string())).unwrap_or(JsonValue::Null)));
+        },
+        sql: "ALTER TABLE tasks ADD COLUMN priority TEXT NOT NULL DEFAULT '';
+            UPDATE tasks SET priority = substr(task, 2, 1), task = substr(task, 5) WHERE task GLOB '([A-Z]) *';",
+        item: |_| {},
+        list: |_| {},
+    },
+    Migration {
+        version: 4,
+        description: "the optional due date is a field",
+        fields: |f| {
+            if f.len() == 5 {
+                f.push(String::new());
+            }
+        },
+        json: |members| members.push((String::from("due"), JsonValue::Null)),
+        sql: "ALTER TABLE tasks ADD COLUMN due INTEGER;",
+        item: |_| {},
+        list: |_| {},
+    },
+    Migration {
+        version: 5,
+        description: "the tags +project and @context are moved from the task into the field tags",
+        fields: |f| {
+            if f.len() == 6 {
+                f.push(String::new());
+            }
+        },
+        json: |members| members.push((String::from("tags"), JsonValue::Array(Vec::new()))),
+        sql: "ALTER TABLE tasks ADD COLUMN tags TEXT NOT NULL DEFAULT '';",
+        item: |ds| {
+            let (task, tags) = split_tags(&ds.task);
+            ds.task = task;
+            ds.tags = tags;
+        },
+        list: |_| {},
+    },
+    Migration {
+        version: 6,
+        description: "the time of completion is a field, it's unknown for the tasks done before",
+        fields: |f| {
+            if f.len() == 7 {
+                f.push(String::new());
+            }
+        },
+        json: |members| members.push((String::from("completed"), JsonValue::Null)),
+        sql: "ALTER TABLE tasks ADD COLUMN completed INTEGER;",
+        item: |_| {},
+        list: |_| {},
+    },
+    Migration {
+        version: 7,
+        description: "a task can be the subtask of another task, the ID of the parent is a field",
+        fields: |f| {
+            if f.len() == 8 {
+                f.push(String::new());
+            }
+        },
+        json: |members| members.push((String::from("parent"), JsonValue::Null)),
+        sql: "ALTER TABLE tasks ADD COLUMN parent INTEGER;",
+        item: |_| {},
+        list: |_| {},
+    },
+    Migration {
+        version: 8,
+        description: "a task can depend on other tasks, the IDs of the blocking tasks are a field",
+        fields: |f| {
+            if f.len() == 9 {
+                f.push(String::new());
+            }
+        },
+        json: |members| members.push((String::from("depends"), JsonValue::Array(Vec::new()))),
+        sql: "ALTER TABLE tasks ADD COLUMN depends TEXT NOT NULL DEFAULT '';",
+        item: |_| {},
+        list: |_| {},
+    },
+    Migration {
+        version: 9,
+        description: "a task can recur, the rule of the recurrence is a field",
+        fields: |f| {
+            if f.len() == 10 {
+                f.push(String::new());
+            }
+        },
+        json: |members| members.push((String::from("recur"), JsonValue::Null)),
+        sql: "ALTER TABLE tasks ADD COLUMN recur TEXT NOT NULL DEFAULT '';",
+        item: |_| {},
+        list: |_| {},
+    },
+    Migration {
+        version: 10,
+        description: "a task can have notes with more lines, they are a field",
+        fields: |f| {
+            if f.len() == 11 {
+                f.push(String::new());
+            }
+        },
+        json: |members| members.push((String::from("notes"), JsonValue::String(String::new()))),
+        sql: "ALTER TABLE tasks ADD COLUMN notes TEXT NOT NULL DEFAULT '';",
+        item: |_| {},
+        list: |_| {},
+    },
+    Migration {
+        version: 11,
+        description: "every task has a permanent ID, a UUID, which is a field",
+        fields: |f| {
+            if f.len() == 12 {
+                f.push(new_uid());
+            }
+        },
+        json: |members| members.push((String::from("uid"), JsonValue::String(new_uid()))),
+        sql: "ALTER TABLE tasks ADD COLUMN uid TEXT NOT NULL DEFAULT '';
+            UPDATE tasks SET uid = lower(hex(randomblob(4))) || '-' || lower(hex(randomblob(2))) || '-4'
+                || substr(lower(hex(randomblob(2))), 2) || '-' || substr('89ab', 1 + abs(random()) % 4, 1)
+                || substr(lower(hex(randomblob(2))), 2) || '-' || lower(hex(randomblob(6)));",
+        item: |_| {},
+        list: |_| {},
+    },
+    Migration {
+        version: 12,
+        description: "the tracked work sessions of a task are a field",
+        fields: |f| {
+            if f.len() == 13 {
+                f.push(String::new());
+            }
+        },
+        json: |members| members.push((String::from("sessions"), JsonValue::Array(Vec::new()))),
+        sql: "ALTER TABLE tasks ADD COLUMN sessions TEXT NOT NULL DEFAULT '';",
+        item: |_| {},
+        list: |_| {},
+    },
+    Migration {
+        version: 13,
+        description: "the parent and the dependencies of a task are the permanent IDs instead of the numbers in the list",
+        fields: |_| {},
+        json: |members| {
+            let link = |v: &JsonValue| match v {
+                JsonValue::Number(n) => JsonValue::String(n.to_string()),
+                other => other.clone(),
+            };
+            for (_, value) in members.iter_mut().filter(|(k, _)| k == "parent" || k == "depends") {
+                *value = match &*value {
+                    JsonValue::Array(links) => JsonValue::Array(links.iter().map(link).collect()),
+                    other => link(other),
+                };
+            }
+        },
+        sql: "ALTER TABLE tasks ADD COLUMN parent_uid TEXT;
+            UPDATE tasks SET parent_uid = (SELECT p.uid FROM tasks p WHERE p.idx = tasks.parent);
+            ALTER TABLE tasks DROP COLUMN parent;
+            ALTER TABLE tasks RENAME COLUMN parent_uid TO parent;
+            UPDATE tasks SET depends = coalesce((SELECT group_concat(b.uid, ',') FROM json_each('[' || tasks.depends || ']') d
+                JOIN tasks b ON b.idx = d.value), '');",
+        item: |_| {},
+        list: link_by_uid,
+    },
+];
+
+/// Check the format version of a file, a file of a newer version can't be read.
+pub fn check_version(version: u32) -> Result<(), String> {
+    if version > FORMAT_VERSION {
+        return Err(format!("the file was written by a newer version of todo (format {}, this version reads up to format {}), please update todo",
+            version, FORMAT_VERSION));
+    }
+    if version == 0 {
+        return Err(String::from("unknown format (0)"));
+    }
+    Ok(())
+}
+
+/// The migrations, which are needed for a file of the ```version```.
+pub fn migrations_from(version: u32) -> impl Iterator<Item = &'static Migration> {
+    MIGRATIONS.iter().filter(move |m| m.version > version)
+}
+
+/// Convert the fields of a CSV line of the ```version``` into the current format.
+pub fn migrate_fields(version: u32, fields: &mut Vec<String>) {
+    for m in migrations_from(version) {
+        (m.fields)(fields);
+    }
+}
+
+/// Convert a task of a JSON file of the ```version``` into the current format.
+pub fn migrate_json(version: u32, task: &JsonValue) -> JsonValue {
+    match task {
+        JsonValue::Object(members) => {
+            let mut members = members.clone();
+            for m in migrations_from(version) {
+                (m.json)(&mut members);
+            }
+            JsonValue::Object(members)
+        }
+        other => other.clone(),
+    }
+}
+
+/// Convert a task of the ```version```, after it's read in the current format.
+pub fn migrate_item(version: u32, ds: &mut TodoItem) {
+    for m in migrations_from(version) {
+        (m.item)(ds);
+    }
+}
+
+/// Convert the tasks of a file of the ```version```, after all tasks are read.
+pub fn migrate_list(version: u32, items: &mut [&mut TodoItem]) {
+    for m in migrations_from(version) {
+        (m.list)(items);
+    }
+}
+
+/// Replace the links by a number, the ID of a task in the list or in the file, with the permanent
+/// ID of this task. Older files and the formats without permanent IDs (e.g. Markdown) link the
+/// tasks by their number, a link to a number without a task is removed.
+pub fn link_by_uid(items: &mut [&mut TodoItem]) {
+    let uids: Vec<(u32, String)> = items.iter().map(|ds| (ds.index, ds.uid.clone())).collect();
+    let link = |link: &String| match link.parse::<u32>() {
+        Ok(nr) => uids.iter().find(|(i, _)| *i == nr).map(|(_, uid)| uid.clone()),
+        Err(_) => Some(link.clone()),
+    };
+    for ds in items.iter_mut() {
+        ds.parent = ds.parent.as_ref().and_then(link);
+        ds.depends = ds.depends.iter().filter_map(link).collect();
+    }
+}
+
+/// For internal use.
+///
+/// Remove a todo.txt priority like ```(A) ``` from the start of a task.
+fn take_priority(task: &mut String) -> Option<char> {
+    let p = split_priority(task)?;
+    task.replace_range(..4, "");
+    Some(p)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::todo_export::import_json;
+    use crate::todo_lib::is_uid;
+    use crate::todo_storage::parse_text;
+
+    #[test]
+    fn every_version_has_a_migration() {
+        let versions: Vec<u32> = MIGRATIONS.iter().map(|m| m.version).collect();
+        assert_eq!(versions, (2..=FORMAT_VERSION).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn load_v1_text() {
+        // the old format without header, the task isn't escaped
+        let text = "1;(A) buy milk +shop @home;1714564800;false\n2;semi;colon;1714564800;true\n";
+        let records = parse_text(text).unwrap();
+        assert_eq!(records.len(), 2);
+        let ds = records[0].item.as_ref().unwrap();
+        assert_eq!(ds.task, "buy milk");
+        assert_eq!(ds.priority, Some('A'));
+        assert_eq!(ds.tags, vec!["+shop", "@home"]);
+        assert_eq!(ds.start, 1_714_564_800);
+        assert!(!ds.is_done);
+        assert!(ds.due.is_none() && ds.completed.is_none() && ds.parent.is_none() && ds.recur.is_none());
+        assert!(ds.depends.is_empty() && ds.notes.is_empty() && ds.sessions.is_empty());
+        assert!(is_uid(&ds.uid));
+        let ds = records[1].item.as_ref().unwrap();
+        assert_eq!(ds.task, "semi;colon");
+        assert!(ds.is_done);
+        assert_ne!(records[0].item.as_ref().unwrap().uid, ds.uid);
+    }
+
+    #[test]
+    fn load_v1_numbers_are_no_tags() {
+        let text = "1;call +49 30 123 +work;1714564800;false\n2;mail @ 5pm @5pm;1714564800;false\n";
+        let records = parse_text(text).unwrap();
+        let ds = records[0].item.as_ref().unwrap();
+        assert_eq!(ds.task, "call +49 30 123");
+        assert_eq!(ds.tags, vec!["+work"]);
+        let ds = records[1].item.as_ref().unwrap();
+        assert_eq!(ds.task, "mail @ 5pm @5pm");
+        assert!(ds.tags.is_empty());
+    }
+
+    #[test]
+    fn load_v1_json() {
+        let text = r#"{"format": 1, "tasks": [{"index": 1, "task": "(C) call +work", "start": 1714564800, "is_done": false}]}"#;
+        let items = import_json(text).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].task, "call");
+        assert_eq!(items[0].priority, Some('C'));
+        assert_eq!(items[0].tags, vec!["+work"]);
+        assert!(items[0].sessions.is_empty());
+    }
+
+    #[test]
+    fn versions() {
+        assert!(check_version(1).is_ok());
+        assert!(check_version(FORMAT_VERSION).is_ok());
+        assert!(check_version(0).is_err());
+        assert!(check_version(FORMAT_VERSION + 1).is_err());
+        assert_eq!(migrations_from(FORMAT_VERSION).count(), 0);
+    }
+
+    #[test]
+    fn load_v12_links() {
+        let (a, b, c) = (new_uid(), new_uid(), new_uid());
+        let text = format!("#!todo-format;12\n1;parent;1714564800;false;;;;;;;;;{};\n2;child;1714564800;false;;;;;1;;;;{};\n3;blocked;1714564800;false;;;;;;1,2,9;;;{};\n", a, b, c);
+        let records = parse_text(&text).unwrap();
+        let items: Vec<&TodoItem> = records.iter().map(|r| r.item.as_ref().unwrap()).collect();
+        assert_eq!(items[0].parent, None);
+        assert_eq!(items[1].parent.as_ref(), Some(&a));
+        // a number without a task is removed
+        assert_eq!(items[2].depends, vec![a.clone(), b.clone()]);
+
+        let text = format!(r#"{{"format": 12, "tasks": [
+            {{"index": 1, "task": "parent", "start": 1714564800, "is_done": false, "uid": "{}"}},
+            {{"index": 2, "task": "child", "start": 1714564800, "is_done": false, "parent": 1, "depends": [1], "uid": "{}"}}]}}"#, a, b);
+        let items = import_json(&text).unwrap();
+        assert_eq!((items[1].parent.as_ref(), &items[1].depends), (Some(&a), &vec![a.clone()]));
+    }
+
+}
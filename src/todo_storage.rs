@@ -0,0 +1,920 @@
+//! The storage backends of the ToDo database.
+//!
+//! Every backend implements the ```Storage``` trait, the ```TodoDatabase``` only knows the trait
+//! and doesn't care where the tasks are saved. The backend is selected with the key ```storage```
+//! of the configuration (or the environment variable ```TODO_STORAGE```):
+//!
+//! - ```csv``` the default, a simple text file ```todo.data```
+//! - ```json``` a JSON file ```todo.json```
+//! - ```todotxt``` a file ```todo.txt``` in the format of todo.txt, see <http://todotxt.org>
+//! - ```sqlite``` a SQLite database ```todo.sqlite```, only if todo was built with the feature ```sqlite```
+
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use crate::todo_date::parse_recur;
+use crate::todo_export::{export_todotxt, item_from_todotxt};
+use crate::todo_json::{self, JsonValue};
+use crate::todo_lib::{is_tag, is_uid, TodoItem};
+use crate::todo_migrate::{check_version, link_by_uid, migrate_fields, migrate_item, migrate_json, migrate_list};
+#[cfg(feature = "sqlite")]
+use crate::todo_migrate::migrations_from;
+
+const APP_DATABASE: &str = "todo.data";
+const APP_JSON: &str = "todo.json";
+const APP_TODOTXT: &str = "todo.txt";
+const APP_SQLITE: &str = "todo.sqlite";
+
+// the last second of the year 9999, a later time is an error
+const MAX_TIME: u64 = 253_402_300_799;
+
+// the first line of the database file, followed by the format version, see todo_migrate
+const FORMAT_HEADER: &str = "#!todo-format;";
+pub const FORMAT_VERSION: u32 = 13;
+
+// the first format with the SQLite backend, an old database without user_version has this format
+#[cfg(feature = "sqlite")]
+const SQLITE_FIRST_VERSION: u32 = 2;
+
+/// A task read by a backend, if it couldn't be read the reason is stored instead of the task.
+/// The ```nr``` is the line (or the entry) and ```raw``` the original text, both are needed to
+/// report and quarantine damaged tasks.
+pub struct Record {
+    pub nr: usize,
+    pub raw: String,
+    pub item: Result<TodoItem, String>,
+}
+
+/// The interface of all storage backends.
+pub trait Storage {
+
+    /// The file of the database.
+    fn path(&self) -> &Path;
+
+    /// The same backend for another file, e.g. to read a backup.
+    fn with_path(&self, path: &Path) -> Box<dyn Storage>;
+
+    /// What a record is called in messages, e.g. ```line 4```.
+    fn record_name(&self) -> &'static str {
+        "line"
+    }
+
+    /// The format version of the file, this is only called if the file exists. A backend without
+    /// a format version (todo.txt) has always the current version.
+    fn version(&self) -> Result<u32, String> {
+        Ok(FORMAT_VERSION)
+    }
+
+    /// Read all tasks, this is only called if the file exists. The tasks of an older format are
+    /// converted into the current format.
+    fn load(&self) -> Result<Vec<Record>, String>;
+
+    /// Save all tasks, a backend must never leave a half written database.
+    fn save(&self, items: &[TodoItem]) -> Result<(), String>;
+
+}
+
+/// The default file name of a storage backend.
+pub fn storage_file_name(kind: &str) -> &'static str {
+    match kind {
+        "json" => APP_JSON,
+        "todotxt" | "todo.txt" => APP_TODOTXT,
+        "sqlite" => APP_SQLITE,
+        _ => APP_DATABASE,
+    }
+}
+
+/// Open the storage backend ```kind``` for the file ```path```, without a kind the backend is
+/// selected by the extension of the file.
+pub fn open_storage(path: &Path, kind: &str) -> Result<Box<dyn Storage>, String> {
+
+    let ext = path.extension().unwrap_or_default().to_string_lossy().to_ascii_lowercase();
+    let kind = match (kind.to_ascii_lowercase().as_str(), ext.as_str()) {
+        ("", "json") => String::from("json"),
+        ("", "txt") => String::from("todotxt"),
+        ("", "sqlite") | ("", "db") => String::from("sqlite"),
+        ("", _) => String::from("csv"),
+        (kind, _) => kind.to_string(),
+    };
+
+    let path = path.to_path_buf();
+    match kind.as_str() {
+        "csv" => Ok(Box::new(CsvStorage { path })),
+        "json" => Ok(Box::new(JsonStorage { path })),
+        "todotxt" | "todo.txt" => Ok(Box::new(TodoTxtStorage { path })),
+        #[cfg(feature = "sqlite")]
+        "sqlite" => Ok(Box::new(SqliteStorage { path })),
+        #[cfg(not(feature = "sqlite"))]
+        "sqlite" => Err(String::from("todo was built without SQLite support (feature 'sqlite')")),
+        _ => Err(format!("unknown storage '{}', use csv, json, todotxt or sqlite", kind)),
+    }
+
+}
+
+/// Write a file into a temporary file first and rename it afterwards, so an aborted program
+/// never leaves a half written file.
+pub fn write_atomic(path: &Path, content: &str) -> Result<(), String> {
+
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(".tmp");
+    let temp_name = PathBuf::from(temp_name);
+
+    let file = File::create(&temp_name).map_err(|err| format!("{}: {}", temp_name.display(), err))?;
+    let mut writer = BufWriter::new(file);
+
+    // save the file operation to disk, before the old file is replaced
+    let result = writer.write_all(content.as_bytes())
+        .and_then(|_| writer.flush())
+        .and_then(|_| writer.get_ref().sync_all())
+        .and_then(|_| fs::rename(&temp_name, path));
+
+    if let Err(err) = result {
+        let _ = fs::remove_file(&temp_name);
+        return Err(format!("{}: {}", path.display(), err));
+    }
+    Ok(())
+
+}
+
+/// The simple text file, every task is one line with fields separated by ```;```.
+///
+/// The first line holds the format version, every ```;```, ```\\``` and line break in a field
+/// is escaped with a backslash. Files without a format header are read as the old unescaped
+/// format (version 1).
+pub struct CsvStorage {
+    path: PathBuf,
+}
+
+impl Storage for CsvStorage {
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn with_path(&self, path: &Path) -> Box<dyn Storage> {
+        Box::new(CsvStorage { path: path.to_path_buf() })
+    }
+
+    fn version(&self) -> Result<u32, String> {
+        let content = fs::read(&self.path).map_err(|err| err.to_string())?;
+        Ok(text_version(&String::from_utf8_lossy(&content)))
+    }
+
+    fn load(&self) -> Result<Vec<Record>, String> {
+        let content = fs::read(&self.path).map_err(|err| err.to_string())?;
+        parse_text(&String::from_utf8_lossy(&content))
+    }
+
+    fn save(&self, items: &[TodoItem]) -> Result<(), String> {
+        write_atomic(&self.path, &items_to_text(items))
+    }
+
+}
+
+/// The tasks in the format of the CSV file, this format is also used to store the tasks
+/// in other files, e.g. the journal for undo.
+pub fn items_to_text(items: &[TodoItem]) -> String {
+    let mut s = format!("{}{}\n# ToDo list database\n\n", FORMAT_HEADER, FORMAT_VERSION);
+    for ds in items {
+        s.push_str(format!("{};{};{};{};{};{};{};{};{};{};{};{};{};{}\n", ds.index, escape(&ds.task), ds.start, ds.is_done,
+            ds.priority.map(String::from).unwrap_or_default(), ds.due.map(|d| d.to_string()).unwrap_or_default(),
+            escape(&ds.tags.join(" ")), ds.completed.map(|c| c.to_string()).unwrap_or_default(),
+            ds.parent.as_deref().unwrap_or_default(), links_field(&ds.depends),
+            escape(ds.recur.as_deref().unwrap_or_default()), escape(&ds.notes), ds.uid,
+            sessions_field(&ds.sessions)).as_str());
+    }
+    s
+}
+
+/// The format version of a text in the format of the CSV file, without the format header it's
+/// the old format (version 1).
+pub fn text_version(text: &str) -> u32 {
+    match text.lines().next().and_then(|l| l.strip_prefix(FORMAT_HEADER)) {
+        Some(v) => v.trim().parse::<u32>().unwrap_or(0),
+        None => 1,
+    }
+}
+
+/// Read the tasks in the format of the CSV file, the counterpart of ```items_to_text()```.
+pub fn parse_text(text: &str) -> Result<Vec<Record>, String> {
+
+    let mut records: Vec<Record> = Vec::new();
+
+    let version = text_version(text);
+    check_version(version)?;
+
+    for (nr, line) in text.lines().enumerate() {
+
+        if nr == 0 && line.starts_with(FORMAT_HEADER) {
+            continue;
+        }
+
+        if line.starts_with(';') || line.starts_with('#') || line.starts_with(' ') || line.is_empty() {
+            continue;
+        }
+
+        let mut fields = if version == 1 {
+            split_legacy_line(line)
+        } else {
+            split_escaped(line, ';')
+        };
+        migrate_fields(version, &mut fields);
+
+        let item = parse_fields(&fields).map(|mut ds| {
+            migrate_item(version, &mut ds);
+            ds
+        });
+        records.push(Record { nr: nr + 1, raw: line.to_string(), item });
+
+    }
+
+    migrate_records(version, &mut records);
+    Ok(records)
+
+}
+
+/// For internal use.
+///
+/// Convert the tasks of a file of the ```version``` after all are read, see ```migrate_list()```.
+fn migrate_records(version: u32, records: &mut [Record]) {
+    migrate_list(version, &mut records.iter_mut().filter_map(|r| r.item.as_mut().ok()).collect::<Vec<&mut TodoItem>>());
+}
+
+/// For internal use.
+///
+/// Escape a field for the database file, the backslash, the field separator ```;``` and
+/// line breaks are prefixed with a backslash, so every task keeps on one line.
+pub fn escape(field: &str) -> String {
+    let mut s = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => s.push_str("\\\\"),
+            ';' => s.push_str("\\;"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            _ => s.push(c),
+        }
+    }
+    s
+}
+
+/// For internal use.
+///
+/// Split an escaped line at every unescaped ```sep``` and remove the escaping,
+/// this is the counterpart of ```escape()```.
+pub fn split_escaped(line: &str, sep: char) -> Vec<String> {
+    let mut fields: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => field.push('\n'),
+                Some('r') => field.push('\r'),
+                Some(e) => field.push(e),
+                None => field.push('\\'),
+            }
+        } else if c == sep {
+            fields.push(field);
+            field = String::new();
+        } else {
+            field.push(c);
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// For internal use.
+///
+/// Split a line of the old unescaped format (version 1). A task with a ```;``` was written as it
+/// is, so the first field is the index, the last two fields are the time and the state and all
+/// fields between belong to the task.
+fn split_legacy_line(line: &str) -> Vec<String> {
+    let l_v: Vec<&str> = line.split(';').collect();
+    if l_v.len() < 4 {
+        return l_v.iter().map(|f| f.to_string()).collect();
+    }
+    let last = l_v.len() - 2;
+    vec![
+        l_v[0].to_string(),
+        l_v[1..last].join(";"),
+        l_v[last].to_string(),
+        l_v[last + 1].to_string(),
+    ]
+}
+
+/// For internal use.
+///
+/// Create a ```TodoItem``` from the fields of a database line, if a field can't be read the
+/// reason is returned.
+fn parse_fields(fields: &[String]) -> Result<TodoItem, String> {
+    if fields.len() != 14 {
+        return Err(format!("expected 14 fields, found {}", fields.len()));
+    }
+    let ix = match fields[0].parse::<u32>() {
+        Ok(ix) => ix,
+        Err(_) => return Err(format!("invalid index '{}'", fields[0])),
+    };
+    let time = match fields[2].parse::<u64>() {
+        Ok(time) => time,
+        Err(_) => return Err(format!("invalid start time '{}'", fields[2])),
+    };
+    let done = match fields[3].parse::<bool>() {
+        Ok(done) => done,
+        Err(_) => return Err(format!("invalid state '{}'", fields[3])),
+    };
+    let mut ds = TodoItem::from(ix, fields[1].to_string(), time, done);
+    ds.priority = field_priority(&fields[4])?;
+    ds.due = match fields[5].as_str() {
+        "" => None,
+        due => Some(due.parse::<u64>().map_err(|_| format!("invalid due date '{}'", due))?),
+    };
+    ds.tags = field_tags(&fields[6])?;
+    ds.completed = match fields[7].as_str() {
+        "" => None,
+        completed => Some(completed.parse::<u64>().map_err(|_| format!("invalid completion time '{}'", completed))?),
+    };
+    ds.parent = match fields[8].as_str() {
+        "" => None,
+        parent => Some(field_link(parent).map_err(|_| format!("invalid parent '{}'", parent))?),
+    };
+    ds.depends = field_links(&fields[9])?;
+    ds.recur = field_recur(&fields[10])?;
+    ds.notes = fields[11].to_string();
+    ds.uid = field_uid(&fields[12])?;
+    ds.sessions = field_sessions(&fields[13])?;
+    check_times(ds)
+}
+
+/// For internal use.
+///
+/// Check the times of a task, a date after the year 9999 can't be shown and is an error in the
+/// file, e.g. a time in milliseconds.
+fn check_times(ds: TodoItem) -> Result<TodoItem, String> {
+    let times = [Some(ds.start), ds.due, ds.completed].into_iter().flatten()
+        .chain(ds.sessions.iter().flat_map(|(start, end)| [Some(*start), *end]).flatten());
+    for time in times {
+        if time > MAX_TIME {
+            return Err(format!("invalid time '{}'", time));
+        }
+    }
+    Ok(ds)
+}
+
+/// For internal use.
+///
+/// Read a stored priority, a letter from ```A``` to ```Z``` or empty for no priority.
+fn field_priority(field: &str) -> Result<Option<char>, String> {
+    let mut chars = field.chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => Ok(None),
+        (Some(p), None) if p.is_ascii_uppercase() => Ok(Some(p)),
+        _ => Err(format!("invalid priority '{}'", field)),
+    }
+}
+
+/// For internal use.
+///
+/// Read the stored tags, separated by spaces, every tag starts with ```+``` or ```@```.
+fn field_tags(field: &str) -> Result<Vec<String>, String> {
+    let tags: Vec<String> = field.split_whitespace().map(String::from).collect();
+    match tags.iter().find(|t| !is_tag(t)) {
+        Some(t) => Err(format!("invalid tag '{}'", t)),
+        None => Ok(tags),
+    }
+}
+
+/// For internal use.
+///
+/// The permanent IDs of the tasks, which a task depends on, separated by commas.
+fn links_field(links: &[String]) -> String {
+    links.join(",")
+}
+
+/// For internal use.
+///
+/// Read a link to another task, the permanent ID of the task. A file of an older version links
+/// the task by its number, the number is replaced after all tasks are read (see todo_migrate).
+fn field_link(field: &str) -> Result<String, String> {
+    if is_uid(field) || field.parse::<u32>().is_ok_and(|nr| nr > 0) {
+        Ok(field.to_string())
+    } else {
+        Err(format!("invalid link '{}'", field))
+    }
+}
+
+/// For internal use.
+///
+/// Read the links to the tasks, which a task depends on, this is the counterpart of
+/// ```links_field()```.
+fn field_links(field: &str) -> Result<Vec<String>, String> {
+    field.split(',').filter(|i| !i.is_empty())
+        .map(|i| field_link(i).map_err(|_| format!("invalid dependency '{}'", i)))
+        .collect()
+}
+
+/// For internal use.
+///
+/// Read the rule of a recurring task, an empty field is a task without recurrence.
+fn field_recur(field: &str) -> Result<Option<String>, String> {
+    match field {
+        "" => Ok(None),
+        rule => parse_recur(rule).filter(|r| r == rule).map(Some).ok_or_else(|| format!("invalid recurrence '{}'", rule)),
+    }
+}
+
+/// For internal use.
+///
+/// Read the permanent ID of a task, it must be a UUID in lower case.
+fn field_uid(field: &str) -> Result<String, String> {
+    if is_uid(field) {
+        Ok(field.to_string())
+    } else {
+        Err(format!("invalid id '{}'", field))
+    }
+}
+
+/// The tracked work sessions of a task, e.g. ```1714550400-1714554000,1714640400-```, the end of
+/// a running session is empty.
+pub fn sessions_field(sessions: &[(u64, Option<u64>)]) -> String {
+    sessions.iter()
+        .map(|(start, end)| format!("{}-{}", start, end.map(|e| e.to_string()).unwrap_or_default()))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Read the tracked work sessions of a task, this is the counterpart of ```sessions_field()```.
+pub fn field_sessions(field: &str) -> Result<Vec<(u64, Option<u64>)>, String> {
+    field.split(',').filter(|s| !s.is_empty())
+        .map(|s| {
+            let (start, end) = s.split_once('-').ok_or_else(|| format!("invalid session '{}'", s))?;
+            let start = start.parse::<u64>().map_err(|_| format!("invalid session '{}'", s))?;
+            let end = match end {
+                "" => None,
+                end => Some(end.parse::<u64>().ok().filter(|e| *e >= start).ok_or_else(|| format!("invalid session '{}'", s))?),
+            };
+            Ok((start, end))
+        })
+        .collect()
+}
+
+/// A JSON file, an object with the format version and the array of all tasks.
+pub struct JsonStorage {
+    path: PathBuf,
+}
+
+impl Storage for JsonStorage {
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn with_path(&self, path: &Path) -> Box<dyn Storage> {
+        Box::new(JsonStorage { path: path.to_path_buf() })
+    }
+
+    fn record_name(&self) -> &'static str {
+        "task"
+    }
+
+    fn version(&self) -> Result<u32, String> {
+        let content = fs::read_to_string(&self.path).map_err(|err| err.to_string())?;
+        let json = todo_json::parse(&content)?;
+        Ok(json.get("format").and_then(|v| v.as_u64()).unwrap_or(0).min(u32::MAX as u64) as u32)
+    }
+
+    fn load(&self) -> Result<Vec<Record>, String> {
+
+        let content = fs::read_to_string(&self.path).map_err(|err| err.to_string())?;
+        let json = todo_json::parse(&content)?;
+
+        let version = json.get("format").and_then(|v| v.as_u64()).unwrap_or(0).min(u32::MAX as u64) as u32;
+        check_version(version)?;
+
+        let tasks = match json.get("tasks").and_then(|t| t.as_array()) {
+            Some(tasks) => tasks,
+            None => return Err(String::from("no tasks found")),
+        };
+
+        let mut records: Vec<Record> = tasks.iter().enumerate().map(|(nr, t)| Record {
+            nr: nr + 1,
+            raw: t.to_compact(),
+            item: item_from_json(&migrate_json(version, t)).map(|mut ds| {
+                migrate_item(version, &mut ds);
+                ds
+            }),
+        }).collect();
+
+        migrate_records(version, &mut records);
+        Ok(records)
+
+    }
+
+    fn save(&self, items: &[TodoItem]) -> Result<(), String> {
+        write_atomic(&self.path, &items_to_json(items).to_pretty())
+    }
+
+}
+
+/// The JSON document of all tasks, an object with the format version and the array of tasks.
+pub fn items_to_json(items: &[TodoItem]) -> JsonValue {
+    JsonValue::Object(vec![
+        (String::from("format"), JsonValue::Number(FORMAT_VERSION as f64)),
+        (String::from("tasks"), JsonValue::Array(items.iter().map(item_to_json).collect())),
+    ])
+}
+
+/// Convert a ```TodoItem``` into a JSON object, every field is a key.
+pub fn item_to_json(ds: &TodoItem) -> JsonValue {
+    JsonValue::Object(vec![
+        (String::from("index"), JsonValue::Number(ds.index as f64)),
+        (String::from("task"), JsonValue::String(ds.task.clone())),
+        (String::from("start"), JsonValue::Number(ds.start as f64)),
+        (String::from("is_done"), JsonValue::Bool(ds.is_done)),
+        (String::from("priority"), ds.priority.map(|p| JsonValue::String(p.to_string())).unwrap_or(JsonValue::Null)),
+        (String::from("due"), ds.due.map(|d| JsonValue::Number(d as f64)).unwrap_or(JsonValue::Null)),
+        (String::from("tags"), JsonValue::Array(ds.tags.iter().map(|t| JsonValue::String(t.clone())).collect())),
+        (String::from("completed"), ds.completed.map(|c| JsonValue::Number(c as f64)).unwrap_or(JsonValue::Null)),
+        (String::from("parent"), ds.parent.as_ref().map(|p| JsonValue::String(p.clone())).unwrap_or(JsonValue::Null)),
+        (String::from("depends"), JsonValue::Array(ds.depends.iter().map(|d| JsonValue::String(d.clone())).collect())),
+        (String::from("recur"), ds.recur.as_ref().map(|r| JsonValue::String(r.clone())).unwrap_or(JsonValue::Null)),
+        (String::from("notes"), JsonValue::String(ds.notes.clone())),
+        (String::from("uid"), JsonValue::String(ds.uid.clone())),
+        (String::from("sessions"), JsonValue::Array(ds.sessions.iter().map(|(start, end)| JsonValue::Object(vec![
+            (String::from("start"), JsonValue::Number(*start as f64)),
+            (String::from("end"), end.map(|e| JsonValue::Number(e as f64)).unwrap_or(JsonValue::Null)),
+        ])).collect())),
+    ])
+}
+
+/// Create a ```TodoItem``` from a JSON object, if a key is missing or invalid the reason is returned.
+/// The keys added after the first version are optional, e.g. in a hand-written file.
+pub fn item_from_json(json: &JsonValue) -> Result<TodoItem, String> {
+    let ix = json.get("index").and_then(|v| v.as_u64()).filter(|ix| *ix <= u32::MAX as u64);
+    let ix = ix.ok_or_else(|| String::from("invalid or missing 'index'"))?;
+    let task = json.get("task").and_then(|v| v.as_str()).ok_or_else(|| String::from("invalid or missing 'task'"))?;
+    let time = json.get("start").and_then(|v| v.as_u64()).ok_or_else(|| String::from("invalid or missing 'start'"))?;
+    let done = json.get("is_done").and_then(|v| v.as_bool()).ok_or_else(|| String::from("invalid or missing 'is_done'"))?;
+    let mut ds = TodoItem::from(ix as u32, task.to_string(), time, done);
+    ds.priority = match json.get("priority") {
+        None | Some(JsonValue::Null) => None,
+        Some(p) => field_priority(p.as_str().unwrap_or("?")).map_err(|_| String::from("invalid 'priority'"))?,
+    };
+    ds.due = match json.get("due") {
+        None | Some(JsonValue::Null) => None,
+        Some(d) => Some(d.as_u64().ok_or_else(|| String::from("invalid 'due'"))?),
+    };
+    if let Some(tags) = json.get("tags") {
+        let tags = tags.as_array().ok_or_else(|| String::from("invalid 'tags'"))?;
+        for tag in tags {
+            match tag.as_str() {
+                Some(t) if is_tag(t) => ds.tags.push(t.to_string()),
+                _ => return Err(String::from("invalid 'tags'")),
+            }
+        }
+    }
+    ds.completed = match json.get("completed") {
+        None | Some(JsonValue::Null) => None,
+        Some(c) => Some(c.as_u64().ok_or_else(|| String::from("invalid 'completed'"))?),
+    };
+    ds.parent = match json.get("parent") {
+        None | Some(JsonValue::Null) => None,
+        Some(p) => Some(field_link(p.as_str().unwrap_or("?")).map_err(|_| String::from("invalid 'parent'"))?),
+    };
+    if let Some(depends) = json.get("depends") {
+        let depends = depends.as_array().ok_or_else(|| String::from("invalid 'depends'"))?;
+        for d in depends {
+            ds.depends.push(field_link(d.as_str().unwrap_or("?")).map_err(|_| String::from("invalid 'depends'"))?);
+        }
+    }
+    ds.recur = match json.get("recur") {
+        None | Some(JsonValue::Null) => None,
+        Some(r) => field_recur(r.as_str().unwrap_or("?")).map_err(|_| String::from("invalid 'recur'"))?,
+    };
+    if let Some(notes) = json.get("notes") {
+        ds.notes = notes.as_str().ok_or_else(|| String::from("invalid 'notes'"))?.to_string();
+    }
+    if let Some(uid) = json.get("uid") {
+        ds.uid = field_uid(uid.as_str().unwrap_or("?")).map_err(|_| String::from("invalid 'uid'"))?;
+    }
+    if let Some(sessions) = json.get("sessions") {
+        let sessions = sessions.as_array().ok_or_else(|| String::from("invalid 'sessions'"))?;
+        for s in sessions {
+            let start = s.get("start").and_then(|v| v.as_u64());
+            let end = match s.get("end") {
+                None | Some(JsonValue::Null) => Some(None),
+                Some(e) => e.as_u64().filter(|e| start.is_some_and(|s| *e >= s)).map(Some),
+            };
+            match (start, end) {
+                (Some(start), Some(end)) => ds.sessions.push((start, end)),
+                _ => return Err(String::from("invalid 'sessions'")),
+            }
+        }
+    }
+    check_times(ds)
+}
+
+/// A todo.txt file, the task ID is the line number without the empty lines. The file can be
+/// used with all other todo.txt tools, the fields of a task without a place in todo.txt are tags
+/// like ```id:```, ```parent:```, ```note:``` or ```created:```, see ```item_to_todotxt()```. The time of a task
+/// started or done on the same day as the date of the line is kept, so no field is lost.
+pub struct TodoTxtStorage {
+    path: PathBuf,
+}
+
+impl Storage for TodoTxtStorage {
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn with_path(&self, path: &Path) -> Box<dyn Storage> {
+        Box::new(TodoTxtStorage { path: path.to_path_buf() })
+    }
+
+    fn load(&self) -> Result<Vec<Record>, String> {
+
+        let content = fs::read(&self.path).map_err(|err| err.to_string())?;
+        let mut records: Vec<Record> = Vec::new();
+
+        for (nr, line) in String::from_utf8_lossy(&content).lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut ds = item_from_todotxt(line);
+            ds.index = records.len() as u32 + 1;
+            records.push(Record { nr: nr + 1, raw: line.to_string(), item: Ok(ds) });
+        }
+
+        // todo.txt has no version, a link by the line number is always possible
+        link_by_uid(&mut records.iter_mut().filter_map(|r| r.item.as_mut().ok()).collect::<Vec<&mut TodoItem>>());
+        Ok(records)
+
+    }
+
+    fn save(&self, items: &[TodoItem]) -> Result<(), String> {
+        write_atomic(&self.path, &export_todotxt(items))
+    }
+
+}
+
+/// A SQLite database with the table ```tasks```, the format version is stored as
+/// ```PRAGMA user_version```.
+#[cfg(feature = "sqlite")]
+pub struct SqliteStorage {
+    path: PathBuf,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStorage {
+
+    /// Open the database, a new database gets the table ```tasks``` of the current format.
+    fn open(&self) -> Result<rusqlite::Connection, String> {
+        let conn = rusqlite::Connection::open(&self.path).map_err(|err| err.to_string())?;
+        let exists: bool = conn.query_row("SELECT count(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'tasks'", [], |r| r.get(0))
+            .map_err(|err| err.to_string())?;
+        if !exists {
+            conn.execute_batch(
+                "CREATE TABLE tasks (
+                    position INTEGER PRIMARY KEY,
+                    idx INTEGER NOT NULL,
+                    task TEXT NOT NULL,
+                    start INTEGER NOT NULL,
+                    is_done INTEGER NOT NULL,
+                    priority TEXT NOT NULL DEFAULT '',
+                    due INTEGER,
+                    tags TEXT NOT NULL DEFAULT '',
+                    completed INTEGER,
+                    parent TEXT,
+                    depends TEXT NOT NULL DEFAULT '',
+                    recur TEXT NOT NULL DEFAULT '',
+                    notes TEXT NOT NULL DEFAULT '',
+                    uid TEXT NOT NULL DEFAULT '',
+                    sessions TEXT NOT NULL DEFAULT ''
+                );"
+            ).map_err(|err| err.to_string())?;
+            conn.pragma_update(None, "user_version", FORMAT_VERSION).map_err(|err| err.to_string())?;
+        }
+        Ok(conn)
+    }
+
+    /// The format version of an open database.
+    fn db_version(conn: &rusqlite::Connection) -> Result<u32, String> {
+        let version: u32 = conn.query_row("PRAGMA user_version", [], |r| r.get(0)).map_err(|err| err.to_string())?;
+        Ok(if version == 0 { SQLITE_FIRST_VERSION } else { version })
+    }
+
+    /// Convert the table ```tasks``` into the current format, this is done in the transaction
+    /// of a save (or of a load, which is rolled back).
+    fn migrate(tx: &rusqlite::Transaction, version: u32) -> Result<(), String> {
+        for m in migrations_from(version) {
+            tx.execute_batch(m.sql).map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+
+}
+
+#[cfg(feature = "sqlite")]
+impl Storage for SqliteStorage {
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn with_path(&self, path: &Path) -> Box<dyn Storage> {
+        Box::new(SqliteStorage { path: path.to_path_buf() })
+    }
+
+    fn record_name(&self) -> &'static str {
+        "row"
+    }
+
+    fn version(&self) -> Result<u32, String> {
+        SqliteStorage::db_version(&self.open()?)
+    }
+
+    fn load(&self) -> Result<Vec<Record>, String> {
+
+        let mut conn = self.open()?;
+
+        let version = SqliteStorage::db_version(&conn)?;
+        check_version(version)?;
+
+        // an old table is converted only for reading, the transaction is never committed
+        let tx = conn.transaction().map_err(|err| err.to_string())?;
+        SqliteStorage::migrate(&tx, version)?;
+
+        let mut stmt = tx.prepare("SELECT position, idx, task, start, is_done, priority, due, tags, completed, parent, depends, recur, notes, uid, sessions FROM tasks ORDER BY position")
+            .map_err(|err| err.to_string())?;
+        let rows = stmt.query_map([], |r| {
+            let nr: usize = r.get(0)?;
+            let raw = (1..=14).map(|i| r.get_ref(i).map(|v| format!("{:?}", v))).collect::<rusqlite::Result<Vec<String>>>()?.join(";");
+            let item = (|| -> rusqlite::Result<Result<TodoItem, String>> {
+                let mut ds = TodoItem::from(r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?);
+                ds.due = r.get(6)?;
+                ds.completed = r.get(8)?;
+                let parent: Option<String> = r.get(9)?;
+                ds.notes = r.get(12)?;
+                let (priority, tags, depends, recur, uid, sessions): (String, String, String, String, String, String) =
+                    (r.get(5)?, r.get(7)?, r.get(10)?, r.get(11)?, r.get(13)?, r.get(14)?);
+                Ok(field_priority(&priority).and_then(|p| {
+                    ds.priority = p;
+                    ds.tags = field_tags(&tags)?;
+                    ds.parent = parent.as_deref().map(field_link).transpose()?;
+                    ds.depends = field_links(&depends)?;
+                    ds.recur = field_recur(&recur)?;
+                    ds.uid = field_uid(&uid)?;
+                    ds.sessions = field_sessions(&sessions)?;
+                    migrate_item(version, &mut ds);
+                    check_times(ds)
+                }))
+            })().map_err(|err| err.to_string()).and_then(|item| item);
+            Ok(Record { nr, raw, item })
+        }).map_err(|err| err.to_string())?;
+
+        let mut records = rows.collect::<rusqlite::Result<Vec<Record>>>().map_err(|err| err.to_string())?;
+        migrate_records(version, &mut records);
+        Ok(records)
+
+    }
+
+    fn save(&self, items: &[TodoItem]) -> Result<(), String> {
+
+        let mut conn = self.open()?;
+        let version = SqliteStorage::db_version(&conn)?;
+        check_version(version)?;
+
+        // a transaction is written completely or not at all
+        let tx = conn.transaction().map_err(|err| err.to_string())?;
+        SqliteStorage::migrate(&tx, version)?;
+        tx.execute("DELETE FROM tasks", []).map_err(|err| err.to_string())?;
+        for (i, ds) in items.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO tasks (position, idx, task, start, is_done, priority, due, tags, completed, parent, depends, recur, notes, uid, sessions)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+                rusqlite::params![i + 1, ds.index, ds.task, ds.start, ds.is_done, ds.priority.map(String::from).unwrap_or_default(), ds.due,
+                    ds.tags.join(" "), ds.completed, ds.parent, links_field(&ds.depends), ds.recur.clone().unwrap_or_default(),
+                    ds.notes, ds.uid, sessions_field(&ds.sessions)],
+            ).map_err(|err| err.to_string())?;
+        }
+        tx.pragma_update(None, "user_version", FORMAT_VERSION).map_err(|err| err.to_string())?;
+        tx.commit().map_err(|err| err.to_string())
+
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::todo_lib::new_uid;
+
+    #[test]
+    fn escape_round_trip() {
+        let fields = ["plain", "a;b;", "back\\slash\\", "\\n is no line break", "two\nlines\r\n", "Grüße ☕ 日本;é", ""];
+        for field in fields {
+            let escaped = escape(field);
+            assert!(!escaped.contains('\n') && !escaped.contains('\r'));
+            assert_eq!(split_escaped(&escaped, ';'), vec![field.to_string()]);
+        }
+        let line: Vec<String> = fields.iter().map(|f| escape(f)).collect();
+        assert_eq!(split_escaped(&line.join(";"), ';'), fields);
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let mut ds = TodoItem::from(1, String::from("write; the\nreport\\"), 1_714_564_800, true);
+        ds.priority = Some('B');
+        ds.due = Some(1_715_299_200);
+        ds.tags = vec![String::from("+work"), String::from("@office")];
+        ds.completed = Some(1_714_600_000);
+        ds.parent = Some(new_uid());
+        ds.depends = vec![new_uid(), new_uid()];
+        ds.recur = Some(String::from("1w"));
+        ds.notes = String::from("first; line\nsecond ü");
+        ds.sessions = vec![(1_714_570_000, Some(1_714_573_600)), (1_714_580_000, None)];
+        let text = items_to_text(std::slice::from_ref(&ds));
+        assert_eq!(text_version(&text), FORMAT_VERSION);
+        let records = parse_text(&text).unwrap();
+        assert_eq!(records.len(), 1);
+        let item = records[0].item.as_ref().unwrap();
+        assert_eq!(items_to_text(std::slice::from_ref(item)), text);
+        assert_eq!(item.task, ds.task);
+        assert_eq!(item.notes, ds.notes);
+        assert_eq!(item.sessions, ds.sessions);
+    }
+
+    #[test]
+    fn damaged_lines() {
+        let text = format!("{}{}\n1;task;1714564800;maybe;;;;;;;;;;\n2;task;99999999999999;false;;;;;;;;;;\n", FORMAT_HEADER, FORMAT_VERSION);
+        let records = parse_text(&text).unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records[0].item.as_ref().unwrap_err().contains("invalid state"));
+        assert!(records[1].item.is_err());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let mut ds = TodoItem::from(3, String::from("quote \" and ü"), 1_714_564_800, true);
+        ds.priority = Some('A');
+        ds.due = Some(1_715_299_200);
+        ds.completed = Some(1_714_600_000);
+        ds.notes = String::from("two\nlines");
+        let item = item_from_json(&item_to_json(&ds)).unwrap();
+        assert_eq!(items_to_text(&[item]), items_to_text(&[ds]));
+        assert!(item_from_json(&JsonValue::Object(Vec::new())).is_err());
+    }
+
+    #[test]
+    fn newer_version() {
+        let newer = format!("{}{}\n", FORMAT_HEADER, FORMAT_VERSION + 1);
+        assert_eq!(text_version(&newer), FORMAT_VERSION + 1);
+        assert!(parse_text(&newer).is_err());
+        assert_eq!(text_version("1;task;1714564800;false\n"), 1);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_links_by_uid() {
+        let path = std::env::temp_dir().join(format!("todo-test-{}-links.sqlite", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let (a, b, c) = (new_uid(), new_uid(), new_uid());
+        // a table of version 12, the links are the numbers in the list
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.execute_batch(&format!("CREATE TABLE tasks (position INTEGER PRIMARY KEY, idx INTEGER NOT NULL, task TEXT NOT NULL,
+                start INTEGER NOT NULL, is_done INTEGER NOT NULL, priority TEXT NOT NULL DEFAULT '', due INTEGER,
+                tags TEXT NOT NULL DEFAULT '', completed INTEGER, parent INTEGER, depends TEXT NOT NULL DEFAULT '',
+                recur TEXT NOT NULL DEFAULT '', notes TEXT NOT NULL DEFAULT '', uid TEXT NOT NULL DEFAULT '',
+                sessions TEXT NOT NULL DEFAULT '');
+            INSERT INTO tasks (position, idx, task, start, is_done, parent, depends, uid) VALUES
+                (1, 1, 'parent', 1714564800, 0, NULL, '', '{a}'),
+                (2, 2, 'child', 1714564800, 0, 1, '', '{b}'),
+                (3, 3, 'blocked', 1714564800, 0, NULL, '1,2,9', '{c}');
+            PRAGMA user_version = 12;")).unwrap();
+        drop(conn);
+
+        let storage = SqliteStorage { path: path.clone() };
+        let items: Vec<TodoItem> = storage.load().unwrap().into_iter().map(|r| r.item.unwrap()).collect();
+        assert_eq!(items[1].parent.as_ref(), Some(&a));
+        assert_eq!(items[2].depends, vec![a.clone(), b.clone()]);
+        storage.save(&items).unwrap();
+        assert_eq!(storage.version(), Ok(FORMAT_VERSION));
+        let saved: Vec<TodoItem> = storage.load().unwrap().into_iter().map(|r| r.item.unwrap()).collect();
+        assert_eq!((&saved[1].parent, &saved[2].depends), (&items[1].parent, &items[2].depends));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn sessions_round_trip() {
+        let sessions = vec![(10, Some(20)), (30, None)];
+        assert_eq!(field_sessions(&sessions_field(&sessions)).unwrap(), sessions);
+        assert_eq!(field_sessions("").unwrap(), Vec::new());
+        assert!(field_sessions("x-1").is_err());
+    }
+
+}